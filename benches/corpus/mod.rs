@@ -0,0 +1,118 @@
+//! Generated documents, each stressing a different part of the parser and
+//! emitter.
+
+// Each benchmark only uses some of the documents.
+#![allow(dead_code)]
+
+use std::fmt::Write;
+
+/// Tags nested `depth` levels deep, each with a value and an attribute.
+pub fn nested(depth: usize) -> String {
+    let mut text = String::new();
+    for level in 0..depth {
+        let indent = "    ".repeat(level);
+        writeln!(
+            text,
+            "{}level {} name=\"level {}\" {{",
+            indent, level, level
+        )
+        .unwrap();
+    }
+    for level in (0..depth).rev() {
+        writeln!(text, "{}}}", "    ".repeat(level)).unwrap();
+    }
+    text
+}
+
+/// A square matrix of anonymous tags (as in the README), with `size` values
+/// per row.
+pub fn matrix(size: usize) -> String {
+    let mut text = String::from("matrix {\n");
+    for row in 0..size {
+        text.push_str("   ");
+        for col in 0..size {
+            write!(text, " {}", (row == col) as u8).unwrap();
+        }
+        text.push('\n');
+    }
+    text.push_str("}\n");
+    text
+}
+
+/// A single Base64 value of `size` bytes, split into lines.
+pub fn base64(size: usize) -> String {
+    let data = (0..size).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+    let encoded = base64::encode(&data);
+    let mut text = String::from("blob [\n");
+    for line in encoded.as_bytes().chunks(76) {
+        writeln!(text, "    {}", std::str::from_utf8(line).unwrap()).unwrap();
+    }
+    text.push_str("]\n");
+    text
+}
+
+/// A tag with `count` attributes of assorted types.
+pub fn attributes(count: usize) -> String {
+    let mut text = String::from("config");
+    for i in 0..count {
+        match i % 4 {
+            0 => write!(text, " \\\n    key{}=\"value {}\"", i, i),
+            1 => write!(text, " \\\n    key{}={}", i, i),
+            2 => write!(text, " \\\n    key{}=true", i),
+            _ => write!(text, " \\\n    key{}={}.25", i, i),
+        }
+        .unwrap();
+    }
+    text.push('\n');
+    text
+}
+
+/// `count` tags with a date, a datetime (in UTC and at an offset) and a
+/// duration each.
+pub fn datetimes(count: usize) -> String {
+    let mut text = String::new();
+    for i in 0..count {
+        let (day, hour, min) = (i % 28 + 1, i % 24, i % 60);
+        writeln!(
+            text,
+            "event 2019/03/{:02} 2019/03/{:02} {:02}:{:02}:00-UTC \
+             2019/03/{:02} {:02}:{:02}:30.250-GMT+05:30 {}d:{:02}:{:02}:00",
+            day, day, hour, min, day, hour, min, i, hour, min
+        )
+        .unwrap();
+    }
+    text
+}
+
+/// `count` tags mixing every kind of value, each with a few child tags.
+pub fn mixed(count: usize) -> String {
+    let mut text = String::new();
+    for i in 0..count {
+        writeln!(
+            text,
+            "// Entry {i}\n\
+             ns:entry{i} \"name {i}\" {i} {i}L {i}.5 on null id={i} {{\n    \
+                 created 2019/01/02 12:34:56.789-GMT+01:00 \
+                 timeout=1d:02:03:04\n    \
+                 data [aGVsbG8gd29ybGQ=] `raw\\text`\n    \
+                 matrix {{\n        1 0 0; 0 1 0; 0 0 1\n    }}\n\
+             }}",
+            i = i
+        )
+        .unwrap();
+    }
+    text
+}
+
+/// Every document, by name, at a size which takes a few milliseconds to
+/// parse.
+pub fn all() -> Vec<(&'static str, String)> {
+    vec![
+        ("nested", nested(200)),
+        ("matrix", matrix(300)),
+        ("base64", base64(1 << 20)),
+        ("attributes", attributes(5000)),
+        ("datetimes", datetimes(2000)),
+        ("mixed", mixed(1000)),
+    ]
+}
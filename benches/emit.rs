@@ -0,0 +1,21 @@
+//! Emitting throughput on each of the documents of the corpus.
+
+mod corpus;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+fn emit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("emit");
+    for (name, text) in corpus::all() {
+        let root = sdlang::parse_text(&text).unwrap();
+        let len = sdlang::emit_text(&root).unwrap().len();
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| sdlang::emit_text(&root).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, emit);
+criterion_main!(benches);
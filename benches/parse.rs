@@ -0,0 +1,31 @@
+//! Parsing throughput on each of the documents of the corpus.
+//!
+//! With the `handwritten` feature, both parsers are measured in the same
+//! group, as `pest/<document>` and `handwritten/<document>`:
+//! ```text
+//! cargo bench --bench parse --features handwritten
+//! ```
+
+mod corpus;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use sdlang::ParseOptions;
+
+fn parse(c: &mut Criterion) {
+    let options = ParseOptions::default();
+    let mut group = c.benchmark_group("parse");
+    for (name, text) in corpus::all() {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function(format!("pest/{}", name), |b| {
+            b.iter(|| sdlang::parse_text_pest(&text, &options).unwrap())
+        });
+        #[cfg(feature = "handwritten")]
+        group.bench_function(format!("handwritten/{}", name), |b| {
+            b.iter(|| sdlang::parse_text(&text).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+//! Looking up attributes and child tags in parsed documents.
+
+mod corpus;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use std::hint::black_box;
+
+fn query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query");
+
+    let root = sdlang::parse_text(&corpus::attributes(5000)).unwrap();
+    let config = &root.tags[0];
+    group.bench_function("attr", |b| {
+        b.iter(|| {
+            (0..5000)
+                .step_by(97)
+                .filter_map(|i| config.attr(&format!("key{}", black_box(i))))
+                .count()
+        })
+    });
+
+    let root = sdlang::parse_text(&corpus::mixed(1000)).unwrap();
+    group.bench_function("tag", |b| {
+        b.iter(|| {
+            root.tags
+                .iter()
+                .filter_map(|entry| entry.tag(black_box("matrix")))
+                .map(|matrix| matrix.tags.len())
+                .sum::<usize>()
+        })
+    });
+
+    let root = sdlang::parse_text(&corpus::nested(200)).unwrap();
+    group.bench_function("nested", |b| {
+        b.iter(|| {
+            let mut tag = &root;
+            for level in 0..200 {
+                tag = tag.tag(black_box(&format!("level{}", level)))?;
+            }
+            Some(tag)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, query);
+criterion_main!(benches);
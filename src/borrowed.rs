@@ -0,0 +1,112 @@
+//! Tags borrowing their text from the parsed document.
+//!
+//! Identifiers, and strings without escapes, are stored as slices of the
+//! source text instead of being copied. Other values have to be converted
+//! from their text anyway, and are stored as normal `Value`s.
+
+use crate::{Attribute, Tag, Value};
+
+use std::borrow::Cow;
+
+/// A value, which may borrow its text from the parsed document.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ValueRef<'a> {
+    /// A string, borrowed if it is raw or has no escapes.
+    String(Cow<'a, str>),
+    /// Any other value (never a `Value::String`).
+    Other(Value),
+}
+
+impl<'a> ValueRef<'a> {
+    /// Converts into a `Value`, copying any borrowed text.
+    pub fn into_owned(self) -> Value {
+        match self {
+            ValueRef::String(text) => Value::String(text.into_owned()),
+            ValueRef::Other(value) => value,
+        }
+    }
+}
+
+impl<'a> From<Value> for ValueRef<'a> {
+    /// Wraps a `Value`, keeping strings as owned text.
+    fn from(value: Value) -> Self {
+        match value {
+            Value::String(text) => ValueRef::String(Cow::Owned(text)),
+            value => ValueRef::Other(value),
+        }
+    }
+}
+
+/// An attribute, which may borrow its text from the parsed document.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AttributeRef<'a> {
+    /// The name of the attribute.
+    pub name: Cow<'a, str>,
+    /// The associated value.
+    pub value: ValueRef<'a>,
+}
+
+impl<'a> AttributeRef<'a> {
+    /// Converts into an `Attribute`, copying any borrowed text.
+    pub fn into_owned(self) -> Attribute {
+        Attribute::new(self.name.into_owned(), self.value.into_owned())
+    }
+}
+
+/// A tag, which may borrow its text from the parsed document.
+///
+/// This mirrors `Tag`; root tags of this kind are returned by
+/// `parse_text_borrowed`.
+/// ```rust
+/// use sdlang::ValueRef;
+/// use std::borrow::Cow;
+///
+/// let text = r#"name "plain" "esc\"aped""#;
+/// let root = sdlang::parse_text_borrowed(text).unwrap();
+/// let tag = &root.tags[0];
+/// assert!(matches!(tag.name, Cow::Borrowed("name")));
+/// assert!(matches!(tag.values[0], ValueRef::String(Cow::Borrowed("plain"))));
+/// assert!(matches!(tag.values[1], ValueRef::String(Cow::Owned(_))));
+/// assert_eq!(root.into_owned(), sdlang::parse_text(text).unwrap());
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct TagRef<'a> {
+    /// The namespace (if any) of the tag.
+    pub namespace: Option<Cow<'a, str>>,
+    /// The name of the tag. `""` if none was given.
+    pub name: Cow<'a, str>,
+    /// A list of values.
+    pub values: Vec<ValueRef<'a>>,
+    /// A list of attributes.
+    pub attrs: Vec<AttributeRef<'a>>,
+    /// A list of subtags (empty vector if none exist).
+    pub tags: Vec<TagRef<'a>>,
+}
+
+impl<'a> TagRef<'a> {
+    /// Creates a new, empty tag with the given name.
+    pub fn new(name: Cow<'a, str>) -> Self {
+        TagRef {
+            namespace: None,
+            name,
+            values: Vec::new(),
+            attrs: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Converts into a `Tag`, copying any borrowed text.
+    pub fn into_owned(self) -> Tag {
+        Tag {
+            namespace: self.namespace.map(Cow::into_owned),
+            name: self.name.into_owned(),
+            values: self.values.into_iter().map(ValueRef::into_owned).collect(),
+            attrs: self
+                .attrs
+                .into_iter()
+                .map(AttributeRef::into_owned)
+                .collect(),
+            tags: self.tags.into_iter().map(TagRef::into_owned).collect(),
+        }
+    }
+}
@@ -0,0 +1,402 @@
+//! Lossless concrete syntax trees.
+//!
+//! Unlike `Tag`, which only keeps the data of a document, a concrete syntax
+//! tree keeps every character of the source text: whitespace, comments and
+//! separators are stored as tokens alongside the data, so writing the tree
+//! back out reproduces the source exactly.
+//!
+//! The tree is made of nodes (the document, tags and attributes), each of
+//! which holds a list of tokens and child nodes in source order.
+
+use crate::grammar::{self, Rule};
+use crate::parse;
+use crate::span::{AttrSpans, Span, TagSpans};
+use crate::{Attribute, Error, ParseOptions, Result, Tag, Value};
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The kind of a token.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenKind {
+    /// Spaces, tabs and carriage returns.
+    Whitespace,
+    /// A line continuation (`\` followed by a newline).
+    Continuation,
+    /// A newline, which ends a tag.
+    Newline,
+    /// A comment of any style. Line comments do not include their newline.
+    Comment,
+    /// A semicolon, which ends a tag.
+    Semicolon,
+    /// The namespace of a tag.
+    Namespace,
+    /// The colon following a namespace.
+    Colon,
+    /// The name of a tag or attribute.
+    Name,
+    /// The equals sign of an attribute.
+    Equals,
+    /// A value, as written in the source.
+    Value,
+    /// The opening brace of a list of child tags.
+    OpenBrace,
+    /// The closing brace of a list of child tags.
+    CloseBrace,
+}
+
+impl TokenKind {
+    /// Whether tokens of this kind carry no data.
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            TokenKind::Whitespace | TokenKind::Continuation | TokenKind::Comment
+        )
+    }
+}
+
+/// A piece of source text.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Token {
+    /// The kind of token.
+    pub kind: TokenKind,
+    /// The exact source text.
+    pub text: String,
+}
+
+impl Token {
+    /// Creates a new token.
+    pub fn new(kind: TokenKind, text: String) -> Self {
+        Token { kind, text }
+    }
+}
+
+/// The kind of a node.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NodeKind {
+    /// A whole document, holding the top-level tags.
+    Document,
+    /// A tag, including its attributes and child tags.
+    Tag,
+    /// An attribute.
+    Attribute,
+}
+
+/// A child of a node.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Element {
+    /// A token.
+    Token(Token),
+    /// A child node.
+    Node(Node),
+}
+
+/// A syntactic item, made up of tokens and child nodes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Node {
+    /// The kind of node.
+    pub kind: NodeKind,
+    /// The tokens and child nodes, in source order.
+    pub children: Vec<Element>,
+}
+
+impl Node {
+    /// Creates a new, empty node.
+    pub fn new(kind: NodeKind) -> Self {
+        Node {
+            kind,
+            children: Vec::new(),
+        }
+    }
+
+    /// Iterates over the tokens directly within this node.
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        self.children.iter().filter_map(|e| match e {
+            Element::Token(token) => Some(token),
+            Element::Node(_) => None,
+        })
+    }
+
+    /// Iterates over the nodes directly within this node.
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|e| match e {
+            Element::Node(node) => Some(node),
+            Element::Token(_) => None,
+        })
+    }
+
+    /// Finds the first token of the given kind directly within this node.
+    pub fn token(&self, kind: TokenKind) -> Option<&Token> {
+        self.tokens().find(|t| t.kind == kind)
+    }
+
+    /// Returns the namespace of a tag.
+    pub fn namespace(&self) -> Option<&str> {
+        self.token(TokenKind::Namespace).map(|t| t.text.as_str())
+    }
+
+    /// Returns the name of a tag or attribute (`""` if there is none).
+    pub fn name(&self) -> &str {
+        self.token(TokenKind::Name).map_or("", |t| t.text.as_str())
+    }
+
+    /// Iterates over the value tokens of a tag.
+    pub fn values(&self) -> impl Iterator<Item = &Token> {
+        self.tokens().filter(|t| t.kind == TokenKind::Value)
+    }
+
+    /// Iterates over the attribute nodes of a tag.
+    pub fn attrs(&self) -> impl Iterator<Item = &Node> {
+        self.nodes().filter(|n| n.kind == NodeKind::Attribute)
+    }
+
+    /// Iterates over the child tag nodes of a document or tag.
+    pub fn tags(&self) -> impl Iterator<Item = &Node> {
+        self.nodes().filter(|n| n.kind == NodeKind::Tag)
+    }
+
+    /// Converts a document or tag node into a `Tag`, parsing its values.
+    ///
+    /// A document becomes a root tag, as returned by `parse_text`.
+    pub fn to_tag(&self) -> Result<Tag> {
+        self.to_tag_with(&ParseOptions::default())
+    }
+
+    /// Converts a document or tag node into a `Tag`, parsing its values with
+    /// the given options.
+    pub fn to_tag_with(&self, options: &ParseOptions) -> Result<Tag> {
+        self.children.iter().try_fold(
+            Tag::new(String::new()),
+            |mut tag, elem| {
+                match elem {
+                    Element::Token(token) => match token.kind {
+                        TokenKind::Namespace => {
+                            tag.namespace = Some(token.text.clone())
+                        }
+                        TokenKind::Name => tag.name = token.text.clone(),
+                        TokenKind::Value => {
+                            tag.values.push(value(&token.text, options)?)
+                        }
+                        _ => {}
+                    },
+                    Element::Node(node) => match node.kind {
+                        NodeKind::Attribute => {
+                            tag.attrs.push(node.to_attr_with(options)?)
+                        }
+                        _ => tag.tags.push(node.to_tag_with(options)?),
+                    },
+                }
+                Ok(tag)
+            },
+        )
+    }
+
+    /// Converts an attribute node into an `Attribute`, parsing its value.
+    pub fn to_attr(&self) -> Result<Attribute> {
+        self.to_attr_with(&ParseOptions::default())
+    }
+
+    /// Converts an attribute node into an `Attribute`, parsing its value with
+    /// the given options.
+    pub fn to_attr_with(&self, options: &ParseOptions) -> Result<Attribute> {
+        let text = self.token(TokenKind::Value).map_or("", |t| &t.text);
+        let value = value(text, options)?;
+        Ok(Attribute::new(self.name().to_string(), value))
+    }
+}
+
+/// Parses the text of a value token.
+fn value(text: &str, options: &ParseOptions) -> Result<Value> {
+    grammar::parse(Rule::value, text)
+        .and_then(|tree| parse::value(tree, options))
+}
+
+impl fmt::Display for Node {
+    /// Writes out the exact source text of the node.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.children.iter().try_for_each(|elem| match elem {
+            Element::Token(token) => f.write_str(&token.text),
+            Element::Node(node) => node.fmt(f),
+        })
+    }
+}
+
+/// A whole SDLang document, as a lossless concrete syntax tree.
+///
+/// It implements `FromStr` for parsing, and `Display` for writing the
+/// document back out exactly as it was written (plus any modifications).
+/// ```rust
+/// use sdlang::Document;
+///
+/// let text = "// Settings\nport 80 /* HTTP */\n";
+/// let doc = text.parse::<Document>().unwrap();
+/// assert_eq!(doc.to_string(), text);
+/// assert_eq!(doc.to_tag().unwrap().tags[0].name, "port");
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Document {
+    /// The root node, of kind `NodeKind::Document`.
+    pub root: Node,
+}
+
+impl Document {
+    /// Parses the text into a `Document` with the given options, returning a
+    /// parse error on failure.
+    ///
+    /// The options are only used to check the values of the text; convert
+    /// the document with `to_tag_with` to use them again.
+    pub fn parse_with(text: &str, options: &ParseOptions) -> Result<Self> {
+        let (_, spans) = crate::parse_text_with_spans_with(text, options)?;
+
+        let mut builder = Builder { text, pos: 0 };
+        let mut root = Node::new(NodeKind::Document);
+        spans.tags.iter().for_each(|tag| {
+            builder.gap(&mut root, tag.span.start.offset);
+            let tag = builder.tag(tag);
+            root.children.push(Element::Node(tag));
+        });
+        builder.gap(&mut root, text.len());
+        Ok(Document { root })
+    }
+
+    /// Converts the document into a root tag, as returned by `parse_text`.
+    pub fn to_tag(&self) -> Result<Tag> {
+        self.root.to_tag()
+    }
+
+    /// Converts the document into a root tag with the given options, as
+    /// returned by `parse_text_with`.
+    pub fn to_tag_with(&self, options: &ParseOptions) -> Result<Tag> {
+        self.root.to_tag_with(options)
+    }
+}
+
+impl fmt::Display for Document {
+    /// Writes out the exact source text of the document.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.root.fmt(f)
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    /// Parses the text into a `Document`, returning a parse error on failure.
+    fn from_str(s: &str) -> Result<Self> {
+        Document::parse_with(s, &ParseOptions::default())
+    }
+}
+
+/// Builds nodes from the spans of a parsed document.
+///
+/// Everything not covered by a span is split up into trivia and punctuation
+/// tokens.
+struct Builder<'a> {
+    text: &'a str,
+    /// The offset up to which the text has been added to the tree.
+    pos: usize,
+}
+
+impl<'a> Builder<'a> {
+    /// Adds the text up to the given offset as trivia and punctuation.
+    fn gap(&mut self, node: &mut Node, end: usize) {
+        let mut text = &self.text[self.pos..end];
+        while !text.is_empty() {
+            let (kind, len) = lex(text);
+            node.children.push(Element::Token(Token::new(
+                kind,
+                text[..len].to_string(),
+            )));
+            text = &text[len..];
+        }
+        self.pos = end;
+    }
+
+    /// Adds the text of the given span as a token.
+    fn token(&mut self, node: &mut Node, kind: TokenKind, span: &Span) {
+        self.gap(node, span.start.offset);
+        let text = self.text[span.range()].to_string();
+        node.children.push(Element::Token(Token::new(kind, text)));
+        self.pos = span.end.offset;
+    }
+
+    fn attr(&mut self, spans: &AttrSpans) -> Node {
+        let mut node = Node::new(NodeKind::Attribute);
+        self.token(&mut node, TokenKind::Name, &spans.name);
+        self.token(&mut node, TokenKind::Value, &spans.value);
+        node
+    }
+
+    fn tag(&mut self, spans: &TagSpans) -> Node {
+        /// A part of a tag, which is added in source order.
+        enum Part<'s> {
+            Token(TokenKind, &'s Span),
+            Attr(&'s AttrSpans),
+            Tag(&'s TagSpans),
+        }
+
+        let mut parts = spans
+            .namespace
+            .iter()
+            .map(|s| Part::Token(TokenKind::Namespace, s))
+            .chain(spans.name.iter().map(|s| Part::Token(TokenKind::Name, s)))
+            .chain(
+                spans
+                    .values
+                    .iter()
+                    .map(|s| Part::Token(TokenKind::Value, s)),
+            )
+            .chain(spans.attrs.iter().map(Part::Attr))
+            .chain(spans.tags.iter().map(Part::Tag))
+            .collect::<Vec<_>>();
+        parts.sort_by_key(|part| match part {
+            Part::Token(_, span) => span.start.offset,
+            Part::Attr(attr) => attr.span.start.offset,
+            Part::Tag(tag) => tag.span.start.offset,
+        });
+
+        let mut node = Node::new(NodeKind::Tag);
+        parts.into_iter().for_each(|part| match part {
+            Part::Token(kind, span) => self.token(&mut node, kind, span),
+            Part::Attr(attr) => {
+                self.gap(&mut node, attr.span.start.offset);
+                let attr = self.attr(attr);
+                node.children.push(Element::Node(attr));
+            }
+            Part::Tag(tag) => {
+                self.gap(&mut node, tag.span.start.offset);
+                let tag = self.tag(tag);
+                node.children.push(Element::Node(tag));
+            }
+        });
+        self.gap(&mut node, spans.span.end.offset);
+        node
+    }
+}
+
+/// Returns the kind and length of the token at the start of the text.
+///
+/// The text must be a gap between the spans of a parsed document, so it only
+/// contains trivia and punctuation.
+fn lex(text: &str) -> (TokenKind, usize) {
+    let line_end = text.find('\n').unwrap_or(text.len());
+    match text.as_bytes()[0] {
+        b' ' | b'\t' | b'\r' => (
+            TokenKind::Whitespace,
+            text.find(|c| !matches!(c, ' ' | '\t' | '\r'))
+                .unwrap_or(text.len()),
+        ),
+        b'\n' => (TokenKind::Newline, 1),
+        b'\\' => (TokenKind::Continuation, 2),
+        b';' => (TokenKind::Semicolon, 1),
+        b':' => (TokenKind::Colon, 1),
+        b'=' => (TokenKind::Equals, 1),
+        b'{' => (TokenKind::OpenBrace, 1),
+        b'}' => (TokenKind::CloseBrace, 1),
+        _ if text.starts_with("/*") => {
+            (TokenKind::Comment, text[2..].find("*/").unwrap() + 4)
+        }
+        b'/' | b'-' | b'#' => (TokenKind::Comment, line_end),
+        _ => unreachable!(),
+    }
+}
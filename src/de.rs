@@ -0,0 +1,482 @@
+//! Serde deserialization from SDLang documents.
+//!
+//! Rust data is mapped onto tags as follows:
+//!
+//! - The document root must be a struct or a map. Its fields become top-level
+//!   tags named after the field.
+//! - Primitives become values: integers are `Number`s, `f32`s and `f64`s are
+//!   `Decimal`s of the `Float` and `Double` kinds, `char`s are `Char`s and
+//!   strings are `String`s. Byte arrays (e.g through `serde_bytes`) are
+//!   `Base64` blocks.
+//! - A field holding a primitive is a tag with a single value: `port 8080`.
+//!   When deserializing, attributes are accepted in place of child tags, so
+//!   `server port=8080` also works.
+//! - A field holding a struct or a map is a tag with child tags, one per
+//!   field: `server { port 8080 }`.
+//! - A field holding a sequence of primitives is a tag with multiple values:
+//!   `ports 80 443`. A sequence of sequences is a tag with anonymous child
+//!   tags, like the rows of a matrix. A sequence of structs, maps or enums is
+//!   a series of tags with the same name.
+//! - `None` fields are left out; `()` and unit structs are `null`.
+//! - Unit enum variants are strings holding the variant's name. Other
+//!   variants are tags whose namespace is the field name and whose name is
+//!   the variant name: `shape:Circle 1.5`, or `shape:Rect { w 1; h 2 }`.
+//!
+//! SDLang dates, datetimes and non-negative durations deserialize into the
+//! matching `chrono` types and `std::time::Duration` (dates and datetimes are
+//! handed to the visitor as ISO 8601 strings, as `chrono` writes them).
+//! Datetimes without a timezone are written without an offset, and
+//! deserialize into `NaiveDateTime`. When serializing, strings in exactly that
+//! format become dates and datetimes again, and sequences of `u8` (such as
+//! `Vec<u8>`) become Base64 blocks.
+//!
+//! With the `bigdecimal` feature, big decimals are handed to the visitor as
+//! strings, so that they deserialize into `BigDecimal` (or `String`) without
+//! losing digits.
+//!
+//! Child tags are looked up by their namespace if they have one, and by their
+//! name otherwise.
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, Deserialize, DeserializeOwned, IntoDeserializer};
+use serde::forward_to_deserialize_any;
+
+use chrono::SecondsFormat;
+
+use crate::{DecimalKind, Tag, Value};
+
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The deserialization error type.
+#[derive(Debug)]
+pub enum Error {
+    /// The text could not be parsed.
+    Parse(crate::Error),
+    /// A custom error from a `Deserialize` implementation, usually caused by
+    /// the document not matching the expected data structure.
+    Custom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(err) => err.fmt(f),
+            Error::Custom(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// The deserialization result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Deserializes data from the given root tag.
+///
+/// Look at the module documentation for how tags are mapped onto data.
+pub fn from_tag<'de, T: Deserialize<'de>>(tag: &'de Tag) -> Result<T> {
+    T::deserialize(Node::Tag(tag))
+}
+
+/// Parses the given text and deserializes data from it.
+///
+/// Look at the module documentation for how tags are mapped onto data.
+pub fn from_str<T: DeserializeOwned>(text: &str) -> Result<T> {
+    from_tag(&crate::parse_text(text).map_err(Error::Parse)?)
+}
+
+/// A part of the tag tree being deserialized.
+#[derive(Debug, Clone)]
+enum Node<'de> {
+    /// A single value.
+    Value(&'de Value),
+    /// The contents of a single tag.
+    Tag(&'de Tag),
+    /// All the child tags for a field.
+    Group(Vec<&'de Tag>),
+}
+
+impl<'de> Node<'de> {
+    /// Returns the single value that the node consists of.
+    fn scalar(self) -> Result<&'de Value> {
+        match self {
+            Node::Value(val) => Ok(val),
+            Node::Tag(tag)
+                if tag.values.len() == 1
+                    && tag.attrs.is_empty()
+                    && tag.tags.is_empty() =>
+            {
+                Ok(&tag.values[0])
+            }
+            Node::Tag(tag) => Err(de::Error::custom(format!(
+                "expected a single value in tag '{}'",
+                tag.name
+            ))),
+            Node::Group(tags) => Node::single(tags)?.scalar(),
+        }
+    }
+
+    /// Returns the single tag of a group.
+    fn single(tags: Vec<&'de Tag>) -> Result<Node<'de>> {
+        match tags.as_slice() {
+            [tag] => Ok(Node::Tag(tag)),
+            _ => Err(de::Error::custom(format!(
+                "expected one '{}' tag, found {}",
+                tags[0].namespace.as_ref().unwrap_or(&tags[0].name),
+                tags.len()
+            ))),
+        }
+    }
+
+    /// Returns the elements of a tag used as a sequence, if it is one.
+    ///
+    /// Either the tag has only values, or only anonymous child tags.
+    fn elements(tag: &'de Tag) -> Option<Vec<Node<'de>>> {
+        if !tag.attrs.is_empty() {
+            None
+        } else if tag.tags.is_empty() {
+            Some(tag.values.iter().map(Node::Value).collect())
+        } else if tag.values.is_empty()
+            && tag.tags.iter().all(|t| t.name.is_empty())
+        {
+            Some(tag.tags.iter().map(Node::Tag).collect())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the attributes and grouped child tags of a tag.
+    fn entries(tag: &'de Tag) -> Vec<(&'de str, Node<'de>)> {
+        let mut entries = tag
+            .attrs
+            .iter()
+            .map(|a| (a.name.as_str(), Node::Value(&a.value)))
+            .collect::<Vec<_>>();
+        let mut groups: Vec<(&'de str, Vec<&'de Tag>)> = Vec::new();
+        tag.tags.iter().for_each(|t| {
+            let key = t.namespace.as_ref().unwrap_or(&t.name).as_str();
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, group)) => group.push(t),
+                None => groups.push((key, vec![t])),
+            }
+        });
+        entries.extend(groups.into_iter().map(|(k, g)| (k, Node::Group(g))));
+        entries
+    }
+
+    fn unexpected(&self) -> de::Unexpected<'de> {
+        match self {
+            Node::Value(val) => unexpected(val),
+            Node::Tag(_) => de::Unexpected::Other("tag"),
+            Node::Group(_) => de::Unexpected::Other("list of tags"),
+        }
+    }
+}
+
+fn unexpected(val: &Value) -> de::Unexpected<'_> {
+    match val {
+        Value::String(text) => de::Unexpected::Str(text),
+        Value::Char(ch) => de::Unexpected::Char(*ch),
+        Value::Base64(data) => de::Unexpected::Bytes(data),
+        Value::Date(_) => de::Unexpected::Other("date"),
+        Value::DateTime(..) | Value::NaiveDateTime(_) => {
+            de::Unexpected::Other("datetime")
+        }
+        Value::Duration(_) => de::Unexpected::Other("duration"),
+        Value::Number(..) => de::Unexpected::Other("number"),
+        Value::Decimal(dec, _) => de::Unexpected::Float(*dec),
+        #[cfg(feature = "bigdecimal")]
+        Value::BigDecimal(_) => de::Unexpected::Other("big decimal"),
+        Value::Boolean(val) => de::Unexpected::Bool(*val),
+        Value::Null => de::Unexpected::Unit,
+    }
+}
+
+/// Passes the given value to a visitor.
+fn visit_value<'de, V: de::Visitor<'de>>(
+    val: &'de Value,
+    visitor: V,
+) -> Result<V::Value> {
+    match val {
+        Value::String(text) => visitor.visit_borrowed_str(text),
+        Value::Char(ch) => visitor.visit_char(*ch),
+        Value::Base64(data) => visitor.visit_borrowed_bytes(data),
+        Value::Date(date) => visitor.visit_string(date.to_string()),
+        Value::DateTime(dtime, _) => visitor
+            .visit_string(dtime.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+        // `Debug` gives the ISO 8601 format, without a timezone.
+        Value::NaiveDateTime(dtime) => {
+            visitor.visit_string(format!("{:?}", dtime))
+        }
+        // This is how `std::time::Duration` is deserialized, which rejects
+        // negative durations.
+        Value::Duration(dur) => visit_map(
+            vec![
+                ("secs", dur.num_seconds()),
+                ("nanos", i64::from(dur.subsec_nanos())),
+            ],
+            visitor,
+        ),
+        Value::Number(num, _) => {
+            if let Ok(num) = i64::try_from(*num) {
+                visitor.visit_i64(num)
+            } else if let Ok(num) = u64::try_from(*num) {
+                visitor.visit_u64(num)
+            } else {
+                visitor.visit_i128(*num)
+            }
+        }
+        Value::Decimal(dec, DecimalKind::Float) => {
+            visitor.visit_f32(*dec as f32)
+        }
+        Value::Decimal(dec, DecimalKind::Double) => visitor.visit_f64(*dec),
+        // Big decimals are handed over as text, so that no digit is lost.
+        #[cfg(feature = "bigdecimal")]
+        Value::BigDecimal(dec) => visitor.visit_string(dec.to_string()),
+        Value::Boolean(val) => visitor.visit_bool(*val),
+        Value::Null => visitor.visit_unit(),
+    }
+}
+
+fn visit_seq<'de, I, V>(items: I, visitor: V) -> Result<V::Value>
+where
+    I: IntoIterator,
+    I::Item: IntoDeserializer<'de, Error>,
+    V: de::Visitor<'de>,
+{
+    let mut seq = SeqDeserializer::new(items.into_iter());
+    let res = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(res)
+}
+
+fn visit_map<'de, I, K, T, V>(entries: I, visitor: V) -> Result<V::Value>
+where
+    I: IntoIterator<Item = (K, T)>,
+    K: IntoDeserializer<'de, Error>,
+    T: IntoDeserializer<'de, Error>,
+    V: de::Visitor<'de>,
+{
+    let mut map = MapDeserializer::new(entries.into_iter());
+    let res = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(res)
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Node<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Node<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Node::Value(val) => visit_value(val, visitor),
+            Node::Tag(tag) if tag.attrs.is_empty() && tag.tags.is_empty() => {
+                match tag.values.as_slice() {
+                    [] => visitor.visit_unit(),
+                    [val] => visit_value(val, visitor),
+                    vals => visit_seq(vals.iter().map(Node::Value), visitor),
+                }
+            }
+            Node::Tag(tag) => visit_map(Node::entries(tag), visitor),
+            Node::Group(tags) => match tags.as_slice() {
+                [tag] => Node::Tag(tag).deserialize_any(visitor),
+                _ => visit_seq(tags.into_iter().map(Node::Tag), visitor),
+            },
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.clone().scalar() {
+            Ok(Value::Null) => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Node::Value(Value::Base64(data)) => {
+                visit_seq(data.iter().copied(), visitor)
+            }
+            Node::Tag(tag) => match tag.values.as_slice() {
+                [Value::Base64(_)] => {
+                    Node::Value(&tag.values[0]).deserialize_seq(visitor)
+                }
+                _ => match Node::elements(tag) {
+                    Some(elems) => visit_seq(elems, visitor),
+                    None => Err(de::Error::invalid_type(
+                        Node::Tag(tag).unexpected(),
+                        &visitor,
+                    )),
+                },
+            },
+            // A lone tag may hold the whole sequence, unless it is the
+            // element of a sequence of enums.
+            Node::Group(tags) => match tags.as_slice() {
+                [tag]
+                    if tag.namespace.is_none()
+                        && Node::elements(tag).is_some() =>
+                {
+                    Node::Tag(tag).deserialize_seq(visitor)
+                }
+                _ => visit_seq(tags.into_iter().map(Node::Tag), visitor),
+            },
+            node => Err(de::Error::invalid_type(node.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(
+        self,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Node::Tag(tag) if tag.values.is_empty() => {
+                visit_map(Node::entries(tag), visitor)
+            }
+            Node::Group(tags) => Node::single(tags)?.deserialize_map(visitor),
+            node => match node.clone().scalar() {
+                Ok(val @ Value::Duration(_)) => visit_value(val, visitor),
+                _ => Err(de::Error::invalid_type(node.unexpected(), &visitor)),
+            },
+        }
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Node::Tag(tag) if tag.namespace.is_some() => {
+                visitor.visit_enum(Variant(&tag.name, Node::Tag(tag)))
+            }
+            Node::Group(tags) => {
+                Node::single(tags)?.deserialize_enum("", &[], visitor)
+            }
+            node => match node.scalar()? {
+                Value::String(name) => {
+                    visitor.visit_enum(Variant(name, Node::Value(&Value::Null)))
+                }
+                val => Err(de::Error::invalid_type(unexpected(val), &visitor)),
+            },
+        }
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct identifier
+    }
+}
+
+/// An enum variant, with its name and contents.
+struct Variant<'de>(&'de str, Node<'de>);
+
+impl<'de> de::EnumAccess<'de> for Variant<'de> {
+    type Error = Error;
+    type Variant = Node<'de>;
+
+    fn variant_seed<S: de::DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Node<'de>)> {
+        let name = seed.deserialize(de::value::BorrowedStrDeserializer::<
+            Error,
+        >::new(self.0))?;
+        Ok((name, self.1))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Node<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: de::DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(
+        self,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
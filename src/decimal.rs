@@ -0,0 +1,155 @@
+//! Arbitrary-precision decimals, for numbers with the `BD` suffix.
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// An arbitrary-precision decimal, such as `3.14159265358979323846BD`.
+///
+/// It keeps every digit it was written with, including trailing zeros: like
+/// Java's `BigDecimal`, `1.50` and `1.5` have the same value but not the same
+/// scale, so they are not equal.
+#[derive(PartialEq, Eq, Hash, Clone)]
+pub struct BigDecimal {
+    negative: bool,
+    /// The digits without the point or leading zeros (empty for zero).
+    digits: String,
+    /// The number of digits after the point.
+    scale: u32,
+}
+
+impl BigDecimal {
+    /// Creates the decimal `unscaled * 10^-scale`.
+    pub fn new(unscaled: i128, scale: u32) -> Self {
+        let digits = match unscaled {
+            0 => String::new(),
+            num => num.unsigned_abs().to_string(),
+        };
+        BigDecimal {
+            negative: unscaled < 0,
+            digits,
+            scale,
+        }
+    }
+
+    /// Returns the number of digits after the point.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Checks whether the decimal is below zero.
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Converts the decimal to an integer, if it has no digits after the
+    /// point and fits in 128 bits.
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.scale != 0 {
+            return None;
+        }
+        self.to_string().parse().ok()
+    }
+
+    /// Converts the decimal to the nearest `f64`, losing precision.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap()
+    }
+}
+
+impl From<i128> for BigDecimal {
+    /// Creates a decimal without digits after the point.
+    fn from(v: i128) -> Self {
+        BigDecimal::new(v, 0)
+    }
+}
+
+/// The error for text which is not a decimal.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseBigDecimalError;
+
+impl fmt::Display for ParseBigDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid decimal")
+    }
+}
+
+impl error::Error for ParseBigDecimalError {}
+
+impl FromStr for BigDecimal {
+    type Err = ParseBigDecimalError;
+
+    /// Parses a decimal written as in SDLang, without its suffix (e.g
+    /// `-12.50`). The point and the digits after it are optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, rest) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let (int, frac) = rest.split_once('.').unwrap_or((rest, ""));
+        let is_digits = |part: &str| {
+            !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit())
+        };
+        if !is_digits(int) || (rest.contains('.') && !is_digits(frac)) {
+            return Err(ParseBigDecimalError);
+        }
+
+        let digits = int
+            .chars()
+            .chain(frac.chars())
+            .skip_while(|&c| c == '0')
+            .collect::<String>();
+        Ok(BigDecimal {
+            negative: negative && !digits.is_empty(),
+            digits,
+            scale: u32::try_from(frac.len())
+                .map_err(|_| ParseBigDecimalError)?,
+        })
+    }
+}
+
+impl fmt::Display for BigDecimal {
+    /// Writes the decimal with all of its digits, and without a suffix.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negative {
+            f.write_str("-")?;
+        }
+        let scale = self.scale as usize;
+        let digits = format!("{:0>1$}", self.digits, scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        f.write_str(int)?;
+        if scale > 0 {
+            write!(f, ".{}", frac)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for BigDecimal {
+    /// Formats the same way as `fmt::Display`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Big decimals are (de)serialized as strings, so that no digit is lost.
+#[cfg(feature = "serde")]
+impl serde::Serialize for BigDecimal {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        ser: S,
+    ) -> Result<S::Ok, S::Error> {
+        ser.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BigDecimal {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        de: D,
+    ) -> Result<Self, D::Error> {
+        let text = String::deserialize(de)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,194 @@
+//! A hand-written recursive-descent parser, as a faster alternative to the
+//! Pest grammar.
+//!
+//! It parses straight into tags, without building a parse tree first, and
+//! only handles valid text: on any error it gives up, and the text is parsed
+//! again by Pest, which reports the error. For valid text, the results of the
+//! two parsers must be the same.
+
+use base64 as b64;
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+
+use crate::lexer::{self, Lexer, Token};
+use crate::parse;
+use crate::zone::Zone;
+use crate::{ParseOptions, Tag, Timezone, Value};
+
+/// Parses the given text into a list of tags, or returns `None` if it is not
+/// valid.
+pub fn tagtree(text: &str, opts: &ParseOptions) -> Option<Vec<Tag>> {
+    // Parse events are only reported by the Pest parser.
+    #[cfg(feature = "log")]
+    if log::log_enabled!(target: "sdlang::parse", log::Level::Trace) {
+        return None;
+    }
+
+    let mut parser = Parser {
+        lex: Lexer::new(text),
+        opts,
+    };
+    let tags = parser.tags()?;
+    Some(tags).filter(|_| parser.lex.peek().is_none())
+}
+
+struct Parser<'a, 'o> {
+    lex: Lexer<'a>,
+    opts: &'o ParseOptions,
+}
+
+impl<'a> Parser<'a, '_> {
+    /// Parses a list of tags, up to the end of the text or a closing brace.
+    fn tags(&mut self) -> Option<Vec<Tag>> {
+        let mut tags = Vec::new();
+        loop {
+            self.lex.line_white();
+            match self.lex.peek() {
+                None | Some(b'\n') | Some(b';') | Some(b'}') => {}
+                Some(_) => tags.push(self.tag()?),
+            }
+            self.lex.line_white();
+            if !(self.lex.eat(b'\n') || self.lex.eat(b';')) {
+                return Some(tags);
+            }
+        }
+    }
+
+    /// Parses a tag and its child tags.
+    fn tag(&mut self) -> Option<Tag> {
+        let mut tag = Tag::new(String::new());
+        if let Some(token) = self.lex.value() {
+            tag.values.push(value(token, self.opts)?);
+        } else {
+            let start = self.lex.pos;
+            match self.lex.ident() {
+                Some(namespace) if self.lex.eat(b':') => {
+                    tag.namespace = Some(namespace.to_string());
+                    self.lex.line_white();
+                }
+                _ => self.lex.pos = start,
+            }
+            tag.name = self.lex.ident()?.to_string();
+        }
+
+        // Values and attributes are separated by whitespace, which is left
+        // for the separator of the tag after the last one.
+        loop {
+            let start = self.lex.pos;
+            if !self.lex.line_white() {
+                break;
+            }
+            match self.lex.value() {
+                Some(token) => tag.values.push(value(token, self.opts)?),
+                None => {
+                    self.lex.pos = start;
+                    break;
+                }
+            }
+        }
+        loop {
+            let start = self.lex.pos;
+            if !self.lex.line_white() {
+                break;
+            }
+            match self.attribute() {
+                Some((name, token)) => tag
+                    .attrs
+                    .push((name.to_string(), value(token, self.opts)?).into()),
+                None => {
+                    self.lex.pos = start;
+                    break;
+                }
+            }
+        }
+
+        let start = self.lex.pos;
+        if self.lex.line_white() && self.lex.eat(b'{') {
+            self.lex.white();
+            tag.tags = self.tags()?;
+            self.lex.white();
+            if !self.lex.eat(b'}') {
+                return None;
+            }
+            self.lex.line_white();
+        } else {
+            self.lex.pos = start;
+        }
+        Some(tag)
+    }
+
+    /// Reads the name and value of an attribute.
+    fn attribute(&mut self) -> Option<(&'a str, Token<'a>)> {
+        let name = self.lex.ident()?;
+        if !self.lex.eat(b'=') {
+            return None;
+        }
+        Some((name, self.lex.value()?))
+    }
+}
+
+/// Converts a value token into a value.
+fn value(token: Token, opts: &ParseOptions) -> Option<Value> {
+    Some(match token {
+        Token::String { text, raw: true } => Value::String(text.to_string()),
+        Token::String { text, raw: false } => {
+            Value::String(parse::unescape(text).ok()?)
+        }
+        Token::Char(text) => {
+            Value::Char(parse::unescape(text).ok()?.chars().next()?)
+        }
+        Token::Base64(text) => Value::Base64(
+            b64::decode(
+                text.bytes()
+                    .filter(|&b| lexer::is_base64_char(b))
+                    .collect::<Vec<_>>()
+                    .as_slice(),
+            )
+            .ok()?,
+        ),
+        Token::Date(date) => Value::Date(parse::date_str(date)?),
+        Token::DateTime { date, time, zone } => {
+            let naive = NaiveDateTime::new(
+                parse::date_str(date)?,
+                parse::time_str(time)?,
+            );
+            let utc = || Some(Utc.from_utc_datetime(&naive).fixed_offset());
+            let (dtime, zone) = match zone {
+                None => {
+                    return parse::local(&naive, &opts.default_zone).single()
+                }
+                Some(lexer::Zone::Utc) => (utc(), Timezone::Utc),
+                Some(lexer::Zone::Gmt(None)) => (utc(), Timezone::Gmt),
+                Some(lexer::Zone::Gmt(Some(offset))) => (
+                    parse::tz_offset_str(offset)?
+                        .from_local_datetime(&naive)
+                        .single(),
+                    Timezone::Gmt,
+                ),
+                Some(lexer::Zone::Named(name)) => (
+                    Zone::find(name)?.localize(&naive).single(),
+                    Timezone::Named(name.to_string()),
+                ),
+            };
+            Value::DateTime(dtime?, zone)
+        }
+        Token::Duration {
+            negative,
+            days,
+            time,
+        } => {
+            let time = parse::time_of_day(parse::time_str(time)?);
+            let dur = match days {
+                Some(days) => parse::days_str(days)? + time,
+                None => time,
+            };
+            Value::Duration(if negative { -dur } else { dur })
+        }
+        Token::Decimal { digits, suffix } => {
+            parse::decimal_str(digits, suffix)?
+        }
+        Token::Number { digits, suffix } => parse::number_str(digits, suffix)?,
+        Token::Boolean(val) => Value::Boolean(val),
+        Token::Null => Value::Null,
+    })
+}
@@ -0,0 +1,243 @@
+//! Compiler-style rendering of parsing errors.
+
+use crate::{Error, Span};
+
+use std::fmt;
+
+// ANSI escape codes for the colored output.
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// A parsing error, displayed along with the text it was found in.
+///
+/// Its `Display` implementation shows the message, the location, the offending
+/// line with the span underlined, and a hint on how to fix the error (if there
+/// is one):
+/// ```text
+/// error: Number too large for a number without a suffix
+///  --> config.sdl:1:6
+///   |
+/// 1 | port 3000000000
+///   |      ^^^^^^^^^^
+///   = help: did you mean `3000000000L`? the value exceeds the 32-bit range
+/// ```
+///
+/// The text must be the one that was parsed. Colored output, using ANSI escape
+/// codes, can be enabled with `color`.
+/// ```rust
+/// use sdlang::Diagnostic;
+///
+/// let text = "port 3000000000";
+/// let err = sdlang::parse_text(text).unwrap_err();
+/// let diag = Diagnostic::new(&err, text).name("config.sdl");
+/// assert!(diag.to_string().contains("did you mean `3000000000L`?"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Diagnostic<'a> {
+    error: &'a Error,
+    text: &'a str,
+    name: Option<&'a str>,
+    color: bool,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// Creates a plain diagnostic for the given error in the given text.
+    pub fn new(error: &'a Error, text: &'a str) -> Self {
+        Diagnostic {
+            error,
+            text,
+            name: None,
+            color: false,
+        }
+    }
+
+    /// Sets the name of the source (e.g a file path) shown before the
+    /// position.
+    pub fn name(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Sets whether to color the output using ANSI escape codes.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Returns a hint on how to fix the error, if there is one.
+    pub fn help(&self) -> Option<String> {
+        let source = |span: &Span| self.text.get(span.range()).unwrap_or("");
+        match self.error {
+            Error::NumberOutOfRange { span, suffix } => {
+                let digits = source(span);
+                let range = match suffix.as_str() {
+                    "" => "32-bit",
+                    "L" => "64-bit",
+                    "BD" => "128-bit",
+                    "d" => {
+                        return Some("day counts are limited to 32 bits".into())
+                    }
+                    _ => return None,
+                };
+                let larger = if suffix.is_empty() && digits.parse::<i64>().is_ok()
+                {
+                    Some("L")
+                } else if suffix != "BD"
+                    && (digits.parse::<i128>().is_ok()
+                        || cfg!(feature = "bigdecimal"))
+                {
+                    Some("BD")
+                } else {
+                    None
+                };
+                Some(match larger {
+                    Some(larger) => format!(
+                        "did you mean `{}{}`? the value exceeds the {} range",
+                        digits, larger, range
+                    ),
+                    None => "the value exceeds even the 128-bit range of `BD` \
+                             numbers; the `bigdecimal` feature lifts this limit"
+                        .to_string(),
+                })
+            }
+            Error::UnsupportedBigDecimal { .. } => Some(
+                "decimals with the `BD` suffix need the `bigdecimal` feature; \
+                 without the suffix, they are 64-bit floating-point numbers"
+                    .into(),
+            ),
+            Error::NonexistentLocalTime { span } => Some(format!(
+                "`{}` is skipped over in its timezone, such as by the start \
+                 of daylight saving time",
+                source(span)
+            )),
+            Error::AmbiguousLocalTime { span } => Some(format!(
+                "`{}` occurs twice in its timezone, such as at the end of \
+                 daylight saving time; give it a `-GMT` offset, or parse \
+                 datetimes without a timezone with another `DefaultZone`",
+                source(span)
+            )),
+            // Malformed `\u` escapes only span the `\u`.
+            Error::InvalidEscape { span }
+                if source(span).starts_with("\\u") && source(span).len() > 2 =>
+            {
+                Some(format!(
+                    "`{}` is not a character; surrogates must come in pairs, \
+                     such as `\\ud83d\\ude00`",
+                    source(span)
+                ))
+            }
+            Error::InvalidEscape { .. } => Some(
+                "the escapes are `\\n`, `\\r`, `\\t`, `\\0`, `\\\\`, `\\\"`, \
+                 `\\'`, `\\uXXXX` and a backslash at the end of a line; raw \
+                 strings between backquotes have none"
+                    .into(),
+            ),
+            Error::InvalidDate { span } if source(span).contains('/') => {
+                Some(format!(
+                    "dates are written as `yyyy/mm/dd`, and `{}` does not exist",
+                    source(span)
+                ))
+            }
+            Error::InvalidDate { .. } => Some(
+                "times are written as `hh:mm`, `hh:mm:ss` or `hh:mm:ss.fff` \
+                 (with up to nine digits after the point), with hours below 24"
+                    .into(),
+            ),
+            Error::InvalidTimezone { .. } => Some(
+                "offsets are written as `-GMT+hh:mm` or `-GMT-hh:mm`, and must \
+                 be less than a day"
+                    .into(),
+            ),
+            #[cfg(feature = "tz")]
+            Error::UnknownTimezone { .. } => Some(
+                "timezones are `UTC`, `GMT` offsets, common abbreviations such \
+                 as `JST`, or names of the IANA database such as \
+                 `America/Los_Angeles`"
+                    .into(),
+            ),
+            #[cfg(not(feature = "tz"))]
+            Error::UnknownTimezone { .. } => Some(
+                "timezones are `UTC`, `GMT` offsets or common abbreviations \
+                 such as `JST`; names of the IANA database need the `tz` \
+                 feature"
+                    .into(),
+            ),
+            Error::InvalidBase64 { .. } => Some(
+                "Base64 data is padded with `=` to a multiple of 4 characters"
+                    .into(),
+            ),
+            Error::NestingTooDeep { .. } => Some(format!(
+                "tags may only be nested {} levels deep",
+                crate::recover::MAX_DEPTH
+            )),
+            Error::Syntax { .. } | Error::Io(_) => None,
+        }
+    }
+
+    /// Writes the text in the given style, if coloring is enabled.
+    fn paint(
+        &self,
+        f: &mut fmt::Formatter,
+        style: &str,
+        text: &str,
+    ) -> fmt::Result {
+        if self.color {
+            write!(f, "{}{}{}", style, text, RESET)
+        } else {
+            f.write_str(text)
+        }
+    }
+}
+
+impl<'a> fmt::Display for Diagnostic<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.paint(f, RED, "error")?;
+        self.paint(f, BOLD, &format!(": {}", self.error.message()))?;
+        let span = match self.error.span() {
+            Some(span) => span,
+            None => return writeln!(f),
+        };
+
+        let start = span.start;
+        let line = self.text.lines().nth(start.line - 1).unwrap_or("");
+        let gutter = " ".repeat(start.line.to_string().len());
+        writeln!(f)?;
+        self.paint(f, BLUE, &format!("{}--> ", gutter))?;
+        if let Some(name) = self.name {
+            write!(f, "{}:", name)?;
+        }
+        writeln!(f, "{}", start)?;
+        self.paint(f, BLUE, &format!("{} |", gutter))?;
+        writeln!(f)?;
+
+        // Tabs are expanded, so that the underline stays aligned.
+        let expand = |text: &str| text.replace('\t', "    ");
+        let before = line.chars().take(start.col - 1).collect::<String>();
+        let width = if span.end.line == start.line {
+            span.end.col - start.col
+        } else {
+            line.chars().count() + 1 - start.col
+        };
+        let under = line
+            .chars()
+            .skip(start.col - 1)
+            .take(width)
+            .collect::<String>();
+        self.paint(f, BLUE, &format!("{} | ", start.line))?;
+        writeln!(f, "{}", expand(line))?;
+        self.paint(f, BLUE, &format!("{} | ", gutter))?;
+        write!(f, "{}", " ".repeat(expand(&before).chars().count()))?;
+        let carets = expand(&under).chars().count().max(1);
+        self.paint(f, RED, &"^".repeat(carets))?;
+        writeln!(f)?;
+
+        if let Some(help) = self.help() {
+            self.paint(f, BLUE, &format!("{} = ", gutter))?;
+            self.paint(f, BOLD, "help")?;
+            writeln!(f, ": {}", help)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,569 @@
+//! Format-preserving edits of documents.
+//!
+//! Every edit changes as little of the source text as possible: new items are
+//! written in the default layout and indented to match their surroundings,
+//! and removed items take their separating whitespace with them. Comments and
+//! the layout of everything else are left untouched.
+//!
+//! Tags are addressed by paths of names, each of which selects the first child
+//! tag with that name (written `ns:name` for namespaced tags), or the one at
+//! the given index among the child tags with that name (written `name[1]` for
+//! the second one). The empty path refers to the document itself, which only
+//! has child tags.
+
+use crate::cst::{Document, Element, Node, NodeKind, Token, TokenKind};
+use crate::{emit, PrettyConfig, Tag, Value};
+
+use std::fmt;
+
+/// The error type for edits of a `Document`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditError {
+    /// No tag exists at the given path (or at the given index of it).
+    NoTag,
+    /// The tag has no value at the given index.
+    NoValue(usize),
+    /// The tag has no attribute with the given name.
+    NoAttr(String),
+    /// The new item cannot be represented in SDLang, or the edit would leave
+    /// an anonymous tag without values.
+    Unrepresentable,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::NoTag => f.write_str("No such tag"),
+            EditError::NoValue(index) => {
+                write!(f, "No value at index {}", index)
+            }
+            EditError::NoAttr(name) => write!(f, "No attribute named {}", name),
+            EditError::Unrepresentable => {
+                f.write_str("Edit cannot be represented in SDLang")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+type Result<T> = std::result::Result<T, EditError>;
+
+impl Document {
+    /// Finds the tag at the given path.
+    pub fn tag(&self, path: &[&str]) -> Option<&Node> {
+        path.iter().try_fold(&self.root, |node, segment| {
+            let (name, index) = parse_segment(segment);
+            node.tags().filter(|tag| is_named(tag, name)).nth(index)
+        })
+    }
+
+    /// Replaces the value at the given index of the tag at the given path.
+    pub fn set_value(
+        &mut self,
+        path: &[&str],
+        index: usize,
+        value: &Value,
+    ) -> Result<()> {
+        let node = self.tag_mut(path)?;
+        let pos = *values(node).get(index).ok_or(EditError::NoValue(index))?;
+        node.children[pos] = token(TokenKind::Value, render(value)?);
+        fix_durations(node)
+    }
+
+    /// Inserts a value at the given index of the tag at the given path,
+    /// shifting all values after it to the right.
+    pub fn insert_value(
+        &mut self,
+        path: &[&str],
+        index: usize,
+        value: &Value,
+    ) -> Result<()> {
+        let node = self.tag_mut(path)?;
+        let new = token(TokenKind::Value, render(value)?);
+        let vals = values(node);
+        if let Some(&pos) = vals.get(index) {
+            insert(node, pos, vec![new, space()]);
+        } else if index == vals.len() {
+            let pos = vals
+                .last()
+                .copied()
+                .or_else(|| position(node, TokenKind::Name))
+                .ok_or(EditError::Unrepresentable)?;
+            insert(node, pos + 1, vec![space(), new]);
+        } else {
+            return Err(EditError::NoValue(index));
+        }
+        fix_durations(node)
+    }
+
+    /// Removes the value at the given index of the tag at the given path.
+    pub fn remove_value(&mut self, path: &[&str], index: usize) -> Result<()> {
+        let node = self.tag_mut(path)?;
+        let vals = values(node);
+        let pos = *vals.get(index).ok_or(EditError::NoValue(index))?;
+        if vals.len() == 1 && position(node, TokenKind::Name).is_none() {
+            return Err(EditError::Unrepresentable);
+        }
+        remove_spaced(node, pos);
+        fix_durations(node)
+    }
+
+    /// Sets the attribute with the given name of the tag at the given path,
+    /// adding it after the existing attributes if there is none.
+    pub fn set_attr(
+        &mut self,
+        path: &[&str],
+        name: &str,
+        value: &Value,
+    ) -> Result<()> {
+        let node = self.tag_mut(path)?;
+        let text = render(value)?;
+        if let Some(attr) = attr_mut(node, name) {
+            let pos = position(attr, TokenKind::Value).unwrap();
+            attr.children[pos] = token(TokenKind::Value, text);
+            return Ok(());
+        }
+
+        let mut ident = String::new();
+        emit::ident(&mut ident, name)
+            .map_err(|_| EditError::Unrepresentable)?;
+        let mut attr = Node::new(NodeKind::Attribute);
+        attr.children = vec![
+            token(TokenKind::Name, ident),
+            token(TokenKind::Equals, "=".to_string()),
+            token(TokenKind::Value, text),
+        ];
+        let pos = node
+            .children
+            .iter()
+            .rposition(|e| match e {
+                Element::Token(t) => {
+                    t.kind == TokenKind::Name || t.kind == TokenKind::Value
+                }
+                Element::Node(n) => n.kind == NodeKind::Attribute,
+            })
+            .unwrap();
+        insert(node, pos + 1, vec![space(), Element::Node(attr)]);
+        Ok(())
+    }
+
+    /// Removes the attribute with the given name of the tag at the given path.
+    pub fn remove_attr(&mut self, path: &[&str], name: &str) -> Result<()> {
+        let node = self.tag_mut(path)?;
+        let pos = node
+            .children
+            .iter()
+            .position(|e| match e {
+                Element::Node(n) => is_attr(n, name),
+                Element::Token(_) => false,
+            })
+            .ok_or_else(|| EditError::NoAttr(name.to_string()))?;
+        remove_spaced(node, pos);
+        Ok(())
+    }
+
+    /// Inserts a child tag at the given index of the tag at the given path.
+    ///
+    /// The new tag is placed on a line of its own where possible.
+    pub fn insert_tag(
+        &mut self,
+        path: &[&str],
+        index: usize,
+        tag: &Tag,
+    ) -> Result<()> {
+        let (node, indent) = self.find(path)?;
+        let child = child_indent(node, &indent);
+        let unit = indent_unit(&indent, &child);
+        let new = Element::Node(render_tag(tag, &child, &unit)?);
+
+        let tags = tags(node);
+        if let Some(&pos) = tags.get(index) {
+            let start = pos
+                - ends_with(&node.children[..pos], TokenKind::Whitespace)
+                    as usize;
+            if start == 0
+                || is_token(&node.children[start - 1], TokenKind::Newline)
+            {
+                // Take the place of the tag, which moves down a line.
+                let mut elems = whitespace(&child);
+                elems.extend(vec![new, newline()]);
+                insert(node, start, elems);
+            } else {
+                insert(node, pos, vec![new, semicolon(), space()]);
+            }
+        } else if index != tags.len() {
+            return Err(EditError::NoTag);
+        } else if let Some(&last) = tags.last() {
+            let end = skip_trivia(node, last + 1);
+            match node.children.get(end) {
+                Some(e) if is_token(e, TokenKind::Newline) => {
+                    let mut elems = whitespace(&child);
+                    elems.extend(vec![new, newline()]);
+                    insert(node, end + 1, elems);
+                }
+                None if node.kind == NodeKind::Document => {
+                    let mut elems = vec![newline()];
+                    elems.extend(whitespace(&child));
+                    elems.push(new);
+                    insert(node, end, elems);
+                }
+                _ => insert(node, last + 1, vec![semicolon(), space(), new]),
+            }
+        } else if node.kind == NodeKind::Document {
+            // Start a new line at the end of the document.
+            if node
+                .children
+                .last()
+                .is_some_and(|e| !is_token(e, TokenKind::Newline))
+            {
+                node.children.push(newline());
+            }
+            node.children.push(new);
+            node.children.push(newline());
+        } else if let Some(open) = position(node, TokenKind::OpenBrace) {
+            let close = position(node, TokenKind::CloseBrace).unwrap();
+            let mut elems = vec![newline()];
+            elems.extend(whitespace(&child));
+            elems.extend(vec![new, newline()]);
+            match node.children[open..close]
+                .iter()
+                .position(|e| is_token(e, TokenKind::Newline))
+            {
+                Some(nl) => insert(node, open + nl + 1, elems.split_off(1)),
+                None => {
+                    elems.extend(whitespace(&indent));
+                    let blank = node.children[open + 1..close]
+                        .iter()
+                        .all(|e| is_token(e, TokenKind::Whitespace));
+                    let end = if blank { close } else { open + 1 };
+                    node.children.splice(open + 1..end, elems);
+                }
+            }
+        } else {
+            // Open a block after the last item of the tag.
+            let pos = node
+                .children
+                .iter()
+                .rposition(|e| match e {
+                    Element::Token(t) => !t.kind.is_trivia(),
+                    Element::Node(_) => true,
+                })
+                .unwrap();
+            let mut elems = vec![
+                space(),
+                token(TokenKind::OpenBrace, "{".to_string()),
+                newline(),
+            ];
+            elems.extend(whitespace(&child));
+            elems.extend(vec![new, newline()]);
+            elems.extend(whitespace(&indent));
+            elems.push(token(TokenKind::CloseBrace, "}".to_string()));
+            insert(node, pos + 1, elems);
+        }
+        Ok(())
+    }
+
+    /// Replaces the tag at the given path.
+    pub fn set_tag(&mut self, path: &[&str], tag: &Tag) -> Result<()> {
+        let (parent, pos, indent) = self.find_child(path)?;
+        let unit = match &parent.children[pos] {
+            Element::Node(old) => {
+                indent_unit(&indent, &child_indent(old, &indent))
+            }
+            Element::Token(_) => unreachable!(),
+        };
+        parent.children[pos] = Element::Node(render_tag(tag, &indent, &unit)?);
+        Ok(())
+    }
+
+    /// Removes the tag at the given path, along with the rest of its line if
+    /// it is on a line of its own.
+    pub fn remove_tag(&mut self, path: &[&str]) -> Result<()> {
+        let (node, pos, _) = self.find_child(path)?;
+        let start = pos
+            - ends_with(&node.children[..pos], TokenKind::Whitespace) as usize;
+        let end = skip_trivia(node, pos + 1);
+        let line_start = start == 0
+            || is_token(&node.children[start - 1], TokenKind::Newline);
+        let next = node.children.get(end);
+
+        if line_start && next.is_none_or(|e| is_token(e, TokenKind::Newline)) {
+            node.children
+                .drain(start..(end + 1).min(node.children.len()));
+        } else if next.is_some_and(|e| is_token(e, TokenKind::Semicolon)) {
+            let end = end + 1;
+            let end = end
+                + node.children[end..]
+                    .iter()
+                    .take_while(|e| is_token(e, TokenKind::Whitespace))
+                    .count();
+            node.children.drain(pos..end);
+        } else if start > 0
+            && is_token(&node.children[start - 1], TokenKind::Semicolon)
+        {
+            node.children.drain(start - 1..pos + 1);
+        } else {
+            node.children.drain(start..pos + 1);
+        }
+        Ok(())
+    }
+
+    /// Finds the tag at the given path, along with the indentation of its line.
+    fn find(&mut self, path: &[&str]) -> Result<(&mut Node, String)> {
+        let mut node = &mut self.root;
+        let mut indent = String::new();
+        for name in path {
+            let pos = child_pos(node, name).ok_or(EditError::NoTag)?;
+            indent = line_indent(node, pos, &indent);
+            node = match &mut node.children[pos] {
+                Element::Node(tag) => tag,
+                Element::Token(_) => unreachable!(),
+            };
+        }
+        Ok((node, indent))
+    }
+
+    /// Finds the parent of the tag at the given (non-empty) path, along with
+    /// the position of the tag in it and the indentation of its line.
+    fn find_child(
+        &mut self,
+        path: &[&str],
+    ) -> Result<(&mut Node, usize, String)> {
+        let (name, parent) = path.split_last().ok_or(EditError::NoTag)?;
+        let (node, indent) = self.find(parent)?;
+        let pos = child_pos(node, name).ok_or(EditError::NoTag)?;
+        let indent = line_indent(node, pos, &indent);
+        Ok((node, pos, indent))
+    }
+
+    /// Finds the tag at the given path, which must not be the document.
+    fn tag_mut(&mut self, path: &[&str]) -> Result<&mut Node> {
+        match self.find(path)? {
+            (node, _) if node.kind == NodeKind::Tag => Ok(node),
+            _ => Err(EditError::NoTag),
+        }
+    }
+}
+
+/// Splits a path segment into a name and the index among the tags with that
+/// name (`0` if it has none).
+fn parse_segment(segment: &str) -> (&str, usize) {
+    segment
+        .strip_suffix(']')
+        .and_then(|rest| rest.split_once('['))
+        .and_then(|(name, index)| Some((name, index.parse().ok()?)))
+        .unwrap_or((segment, 0))
+}
+
+fn is_named(tag: &Node, name: &str) -> bool {
+    match (tag.namespace(), name.split_once(':')) {
+        (Some(ns), Some((name_ns, name))) => {
+            ns == name_ns && tag.name() == name
+        }
+        (None, None) => tag.name() == name,
+        _ => false,
+    }
+}
+
+fn is_attr(attr: &Node, name: &str) -> bool {
+    attr.kind == NodeKind::Attribute && attr.name() == name
+}
+
+fn is_token(elem: &Element, kind: TokenKind) -> bool {
+    matches!(elem, Element::Token(t) if t.kind == kind)
+}
+
+/// Whether the last of the elements is a token of the given kind.
+fn ends_with(elems: &[Element], kind: TokenKind) -> bool {
+    elems.last().is_some_and(|e| is_token(e, kind))
+}
+
+fn token(kind: TokenKind, text: String) -> Element {
+    Element::Token(Token::new(kind, text))
+}
+
+fn space() -> Element {
+    token(TokenKind::Whitespace, " ".to_string())
+}
+
+fn newline() -> Element {
+    token(TokenKind::Newline, "\n".to_string())
+}
+
+fn semicolon() -> Element {
+    token(TokenKind::Semicolon, ";".to_string())
+}
+
+/// Returns the given indentation as elements (none if it is empty).
+fn whitespace(indent: &str) -> Vec<Element> {
+    if indent.is_empty() {
+        Vec::new()
+    } else {
+        vec![token(TokenKind::Whitespace, indent.to_string())]
+    }
+}
+
+fn insert(node: &mut Node, pos: usize, elems: Vec<Element>) {
+    node.children.splice(pos..pos, elems);
+}
+
+/// Returns the position of the first token of the given kind in the node.
+fn position(node: &Node, kind: TokenKind) -> Option<usize> {
+    node.children.iter().position(|e| is_token(e, kind))
+}
+
+/// Returns the positions of the value tokens in the node.
+fn values(node: &Node) -> Vec<usize> {
+    (0..node.children.len())
+        .filter(|&i| is_token(&node.children[i], TokenKind::Value))
+        .collect()
+}
+
+/// Returns the positions of the child tags in the node.
+fn tags(node: &Node) -> Vec<usize> {
+    (0..node.children.len())
+        .filter(|&i| match &node.children[i] {
+            Element::Node(n) => n.kind == NodeKind::Tag,
+            Element::Token(_) => false,
+        })
+        .collect()
+}
+
+/// Returns the position of the child tag selected by the given path segment.
+fn child_pos(node: &Node, segment: &str) -> Option<usize> {
+    let (name, index) = parse_segment(segment);
+    (0..node.children.len())
+        .filter(|&i| match &node.children[i] {
+            Element::Node(n) => n.kind == NodeKind::Tag && is_named(n, name),
+            Element::Token(_) => false,
+        })
+        .nth(index)
+}
+
+fn attr_mut<'a>(node: &'a mut Node, name: &str) -> Option<&'a mut Node> {
+    node.children.iter_mut().find_map(|e| match e {
+        Element::Node(n) if is_attr(n, name) => Some(n),
+        _ => None,
+    })
+}
+
+/// Returns the position of the first element from `pos` which is not
+/// whitespace or a comment.
+fn skip_trivia(node: &Node, pos: usize) -> usize {
+    pos + node.children[pos..]
+        .iter()
+        .take_while(|e| {
+            is_token(e, TokenKind::Whitespace)
+                || is_token(e, TokenKind::Comment)
+        })
+        .count()
+}
+
+/// Removes the element at the given position, along with the whitespace
+/// separating it from the previous one (or the next one, if it comes first).
+fn remove_spaced(node: &mut Node, pos: usize) {
+    if ends_with(&node.children[..pos], TokenKind::Whitespace) {
+        node.children.drain(pos - 1..pos + 1);
+    } else if node
+        .children
+        .get(pos + 1)
+        .is_some_and(|e| is_token(e, TokenKind::Whitespace))
+    {
+        node.children.drain(pos..pos + 2);
+    } else {
+        node.children.remove(pos);
+    }
+}
+
+/// Returns the indentation of the line which the child at the given position
+/// is on, given the indentation of the node itself.
+fn line_indent(node: &Node, pos: usize, indent: &str) -> String {
+    let elems = &node.children[..pos];
+    let start =
+        match elems.iter().rposition(|e| is_token(e, TokenKind::Newline)) {
+            Some(nl) => nl + 1,
+            None if node.kind == NodeKind::Document => 0,
+            // The child is on the same line as the start of the node.
+            None => return indent.to_string(),
+        };
+    match &elems[start..] {
+        [Element::Token(t), ..] if t.kind == TokenKind::Whitespace => {
+            t.text.clone()
+        }
+        _ => String::new(),
+    }
+}
+
+/// Returns the indentation used for child tags of the given node.
+fn child_indent(node: &Node, indent: &str) -> String {
+    tags(node)
+        .first()
+        .map(|&pos| line_indent(node, pos, indent))
+        .filter(|child| {
+            node.kind == NodeKind::Document || child.len() > indent.len()
+        })
+        .unwrap_or_else(|| match node.kind {
+            NodeKind::Document => String::new(),
+            _ => indent.to_string() + &PrettyConfig::default().indent,
+        })
+}
+
+/// Returns the unit of indentation between a tag and its children.
+fn indent_unit(indent: &str, child: &str) -> String {
+    match child.strip_prefix(indent) {
+        Some(unit) if !unit.is_empty() => unit.to_string(),
+        _ => PrettyConfig::default().indent,
+    }
+}
+
+fn render(value: &Value) -> Result<String> {
+    let mut text = String::new();
+    emit::value(&mut text, value).map_err(|_| EditError::Unrepresentable)?;
+    Ok(text)
+}
+
+/// Writes a tag for a line with the given indentation, indenting its children
+/// by the given unit.
+fn render_tag(tag: &Tag, indent: &str, unit: &str) -> Result<Node> {
+    let cfg = PrettyConfig::default().indent(unit);
+    let mut text = String::new();
+    emit::tag(&mut text, tag, &cfg, 0)
+        .map_err(|_| EditError::Unrepresentable)?;
+    let text = text.replace('\n', &format!("\n{}", indent));
+    let doc = text
+        .parse::<Document>()
+        .map_err(|_| EditError::Unrepresentable)?;
+    doc.root
+        .children
+        .into_iter()
+        .find_map(|e| match e {
+            Element::Node(node) => Some(node),
+            Element::Token(_) => None,
+        })
+        .ok_or(EditError::Unrepresentable)
+}
+
+/// Rewrites durations directly following dates with a day count, as they would
+/// otherwise be read back as the time of a datetime.
+fn fix_durations(node: &mut Node) -> Result<()> {
+    let vals = values(node);
+    vals.windows(2).try_for_each(|pair| {
+        let text = |pos: usize| match &node.children[pos] {
+            Element::Token(t) => t.text.clone(),
+            Element::Node(_) => unreachable!(),
+        };
+        let (prev, cur) = (text(pair[0]), text(pair[1]));
+        if let (Ok(Value::Date(_)), Ok(Value::Duration(dur))) =
+            (prev.parse::<Value>(), cur.parse::<Value>())
+        {
+            if !cur.contains('d') {
+                let mut text = String::new();
+                emit::duration_days(&mut text, &dur, true)
+                    .map_err(|_| EditError::Unrepresentable)?;
+                node.children[pair[1]] = token(TokenKind::Value, text);
+            }
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,393 @@
+//! Implements low-level emission routines.
+//!
+//! These routines are the inverse of those in `parse`: each one writes the
+//! given item out as SDLang text, as determined by the function name.
+//!
+//! Every routine fails with `fmt::Error` if the item cannot be represented in
+//! SDLang (e.g an invalid identifier or a non-finite decimal).
+
+use base64 as b64;
+
+use chrono::{Datelike, Offset, Timelike};
+
+#[cfg(feature = "bigdecimal")]
+use crate::decimal::BigDecimal;
+use crate::zone::Zone;
+use crate::{Attribute, BraceStyle, Date, DateTime, PrettyConfig, Tag};
+use crate::{DecimalKind, Duration, NaiveDateTime, NumberKind};
+use crate::{Timezone, Value};
+
+use std::fmt::{self, Write};
+
+/// Writes a character of a string or character literal, escaping it if
+/// needed. The given quote is the one around the literal.
+fn escaped<W: Write>(out: &mut W, ch: char, quote: char) -> fmt::Result {
+    match ch {
+        '\\' => out.write_str("\\\\"),
+        '\n' => out.write_str("\\n"),
+        '\r' => out.write_str("\\r"),
+        '\t' => out.write_str("\\t"),
+        '\x00' => out.write_str("\\0"),
+        c if c == quote => write!(out, "\\{}", c),
+        c => out.write_char(c),
+    }
+}
+
+pub fn string<W: Write>(out: &mut W, text: &str) -> fmt::Result {
+    out.write_char('"')?;
+    text.chars().try_for_each(|ch| escaped(out, ch, '"'))?;
+    out.write_char('"')
+}
+
+pub fn character<W: Write>(out: &mut W, ch: char) -> fmt::Result {
+    out.write_char('\'')?;
+    escaped(out, ch, '\'')?;
+    out.write_char('\'')
+}
+
+pub fn date<W: Write>(out: &mut W, date: &Date) -> fmt::Result {
+    // The grammar only allows four-digit, positive years.
+    if !(0..10_000).contains(&date.year()) {
+        return Err(fmt::Error);
+    }
+    write!(out, "{}", date.format("%Y/%m/%d"))
+}
+
+/// Writes a time, always with its seconds.
+///
+/// The precision the time was written with is not kept: `12:00` is written
+/// back as `12:00:00`, and `12:00:00.5` as `12:00:00.500`.
+pub fn time<W: Write, T: Timelike>(out: &mut W, time: &T) -> fmt::Result {
+    // Leap seconds are stored as an extra second's worth of nanoseconds, and
+    // written as second 60.
+    let leap = time.nanosecond() / 1_000_000_000;
+    write!(
+        out,
+        "{:02}:{:02}:{:02}",
+        time.hour(),
+        time.minute(),
+        time.second() + leap
+    )?;
+    fraction(out, time.nanosecond() % 1_000_000_000)
+}
+
+/// Writes the fraction of a second of a time, if there is one.
+///
+/// It is written in milliseconds, microseconds or nanoseconds, whichever is
+/// the coarsest to hold it exactly.
+fn fraction<W: Write>(out: &mut W, nanos: u32) -> fmt::Result {
+    match nanos {
+        0 => Ok(()),
+        ns if ns % 1_000_000 == 0 => write!(out, ".{:03}", ns / 1_000_000),
+        ns if ns % 1000 == 0 => write!(out, ".{:06}", ns / 1000),
+        ns => write!(out, ".{:09}", ns),
+    }
+}
+
+/// Writes the timezone of a datetime, if it has one.
+///
+/// A named timezone must be known, and give the offset of the datetime at its
+/// instant; otherwise, the text would be read back as a different instant.
+pub fn timezone<W: Write>(
+    out: &mut W,
+    dtime: &DateTime,
+    zone: &Timezone,
+) -> fmt::Result {
+    let secs = dtime.offset().fix().local_minus_utc();
+    match zone {
+        Timezone::Local => return Ok(()),
+        Timezone::Named(name) => {
+            return match Zone::find(name) {
+                Some(found)
+                    if found.offset_at(dtime) == dtime.offset().fix() =>
+                {
+                    write!(out, "-{}", name)
+                }
+                _ => Err(fmt::Error),
+            };
+        }
+        Timezone::Utc if secs == 0 => return out.write_str("-UTC"),
+        Timezone::Gmt if secs == 0 => return out.write_str("-GMT"),
+        Timezone::Utc | Timezone::Gmt => {}
+    }
+    let sign = if secs < 0 { '-' } else { '+' };
+    let mins = secs.abs() / 60;
+    write!(out, "-GMT{}{:02}:{:02}", sign, mins / 60, mins % 60)
+}
+
+pub fn datetime<W: Write>(
+    out: &mut W,
+    dtime: &DateTime,
+    zone: &Timezone,
+) -> fmt::Result {
+    date(out, &dtime.date_naive())?;
+    out.write_char(' ')?;
+    time(out, dtime)?;
+    timezone(out, dtime, zone)
+}
+
+pub fn naive_datetime<W: Write>(
+    out: &mut W,
+    dtime: &NaiveDateTime,
+) -> fmt::Result {
+    date(out, &dtime.date())?;
+    out.write_char(' ')?;
+    time(out, dtime)
+}
+
+pub fn duration<W: Write>(out: &mut W, dur: &Duration) -> fmt::Result {
+    duration_days(out, dur, false)
+}
+
+/// Writes a duration, optionally including the day count even if it is zero.
+///
+/// A positive duration without days directly following a date would be read
+/// back as the time of a datetime.
+pub fn duration_days<W: Write>(
+    out: &mut W,
+    dur: &Duration,
+    force_days: bool,
+) -> fmt::Result {
+    let negative = *dur < Duration::zero();
+    if negative {
+        out.write_char('-')?;
+    }
+    let dur = dur.abs();
+    let secs = dur.num_seconds();
+    let days = secs / (24 * 60 * 60);
+    if days > i64::from(u32::MAX) {
+        return Err(fmt::Error);
+    }
+    if days > 0 || (force_days && !negative) {
+        write!(out, "{}d:", days)?;
+    }
+    write!(
+        out,
+        "{:02}:{:02}:{:02}",
+        secs / (60 * 60) % 24,
+        secs / 60 % 60,
+        secs % 60
+    )?;
+    fraction(out, dur.subsec_nanos() as u32)
+}
+
+pub fn number<W: Write>(
+    out: &mut W,
+    num: i128,
+    kind: NumberKind,
+) -> fmt::Result {
+    if !kind.holds(num) {
+        return Err(fmt::Error);
+    }
+    write!(out, "{}{}", num, kind.suffix())
+}
+
+pub fn decimal<W: Write>(
+    out: &mut W,
+    dec: f64,
+    kind: DecimalKind,
+) -> fmt::Result {
+    if !dec.is_finite() || !kind.holds(dec) {
+        return Err(fmt::Error);
+    }
+    // `Display` never uses exponents, but omits the point for integers.
+    // Floats are written with the fewest digits which read back as the same
+    // `f32`.
+    let text = match kind {
+        DecimalKind::Float => (dec as f32).to_string(),
+        DecimalKind::Double => dec.to_string(),
+    };
+    out.write_str(&text)?;
+    if !text.contains('.') {
+        out.write_str(".0")?;
+    }
+    out.write_str(kind.suffix())
+}
+
+#[cfg(feature = "bigdecimal")]
+pub fn big_decimal<W: Write>(out: &mut W, dec: &BigDecimal) -> fmt::Result {
+    write!(out, "{}BD", dec)
+}
+
+pub fn boolean<W: Write>(out: &mut W, val: bool) -> fmt::Result {
+    out.write_str(if val { "true" } else { "false" })
+}
+
+pub fn base64<W: Write>(out: &mut W, data: &[u8]) -> fmt::Result {
+    write!(out, "[{}]", b64::encode(data))
+}
+
+pub fn value<W: Write>(out: &mut W, val: &Value) -> fmt::Result {
+    match val {
+        Value::String(text) => string(out, text),
+        Value::Char(ch) => character(out, *ch),
+        Value::Base64(data) => base64(out, data),
+        Value::Date(d) => date(out, d),
+        Value::DateTime(dtime, zone) => datetime(out, dtime, zone),
+        Value::NaiveDateTime(dtime) => naive_datetime(out, dtime),
+        Value::Duration(dur) => duration(out, dur),
+        Value::Number(num, kind) => number(out, *num, *kind),
+        Value::Decimal(dec, kind) => decimal(out, *dec, *kind),
+        #[cfg(feature = "bigdecimal")]
+        Value::BigDecimal(dec) => big_decimal(out, dec),
+        Value::Boolean(val) => boolean(out, *val),
+        Value::Null => out.write_str("null"),
+    }
+}
+
+pub fn ident<W: Write>(out: &mut W, name: &str) -> fmt::Result {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || ".$-_".contains(c))
+        // Keywords would be read back as values.
+        && !["true", "false", "on", "off", "null"].contains(&name);
+    if !valid {
+        return Err(fmt::Error);
+    }
+    out.write_str(name)
+}
+
+pub fn attribute<W: Write>(out: &mut W, attr: &Attribute) -> fmt::Result {
+    ident(out, &attr.name)?;
+    out.write_char('=')?;
+    value(out, &attr.value)
+}
+
+pub fn namespace<W: Write>(out: &mut W, namespace: &str) -> fmt::Result {
+    ident(out, namespace)?;
+    out.write_char(':')
+}
+
+/// Writes a tag, indenting its subtags by `depth + 1` levels.
+///
+/// The tag itself is assumed to be indented already; no trailing newline is
+/// written.
+pub fn tag<W: Write>(
+    out: &mut W,
+    tag: &Tag,
+    cfg: &PrettyConfig,
+    depth: usize,
+) -> fmt::Result {
+    let mut line = Line::new(out, cfg, depth);
+    let mut text = String::new();
+
+    // Anonymous tags are identified by their first value.
+    if tag.name.is_empty() {
+        if tag.namespace.is_some() || tag.values.is_empty() {
+            return Err(fmt::Error);
+        }
+    } else {
+        if let Some(ns) = &tag.namespace {
+            namespace(&mut text, ns)?;
+        }
+        ident(&mut text, &tag.name)?;
+        line.push(&text, false)?;
+    }
+
+    let mut prev = None;
+    tag.values.iter().try_for_each(|val| {
+        text.clear();
+        match (prev.replace(val), val) {
+            (Some(Value::Date(_)), Value::Duration(dur)) => {
+                duration_days(&mut text, dur, true)?
+            }
+            _ => value(&mut text, val)?,
+        }
+        line.push(&text, false)
+    })?;
+    tag.attrs.iter().try_for_each(|attr| {
+        text.clear();
+        attribute(&mut text, attr)?;
+        line.push(&text, cfg.attr_per_line)
+    })?;
+
+    if !tag.tags.is_empty() {
+        match cfg.brace_style {
+            BraceStyle::SameLine => line.push("{", false)?,
+            BraceStyle::NextLine => {
+                out.write_str(" \\\n")?;
+                indent(out, cfg, depth)?;
+                out.write_char('{')?;
+            }
+        }
+        out.write_char('\n')?;
+        tags(out, &tag.tags, cfg, depth + 1)?;
+        indent(out, cfg, depth)?;
+        out.write_char('}')?;
+    }
+    Ok(())
+}
+
+/// Writes a list of tags, one per line, at the given depth.
+pub fn tags<W: Write>(
+    out: &mut W,
+    tags: &[Tag],
+    cfg: &PrettyConfig,
+    depth: usize,
+) -> fmt::Result {
+    tags.iter().enumerate().try_for_each(|(i, t)| {
+        if depth == 0 && i > 0 {
+            (0..cfg.top_level_spacing)
+                .try_for_each(|_| out.write_char('\n'))?;
+        }
+        indent(out, cfg, depth)?;
+        tag(out, t, cfg, depth)?;
+        out.write_char('\n')
+    })
+}
+
+fn indent<W: Write>(
+    out: &mut W,
+    cfg: &PrettyConfig,
+    depth: usize,
+) -> fmt::Result {
+    (0..depth).try_for_each(|_| out.write_str(&cfg.indent))
+}
+
+/// The line of a tag being written, which is broken up using line
+/// continuations as the configuration requires.
+struct Line<'a, W: Write> {
+    out: &'a mut W,
+    cfg: &'a PrettyConfig,
+    depth: usize,
+    /// The current column, in characters.
+    col: usize,
+    /// Whether nothing has been written to the line yet.
+    empty: bool,
+}
+
+impl<'a, W: Write> Line<'a, W> {
+    fn new(out: &'a mut W, cfg: &'a PrettyConfig, depth: usize) -> Self {
+        Line {
+            out,
+            cfg,
+            depth,
+            col: depth * cfg.indent.chars().count(),
+            empty: true,
+        }
+    }
+
+    /// Writes a token, separated from the previous one by a space, or by a
+    /// line continuation if it is forced or if the token would overflow.
+    fn push(&mut self, token: &str, force_break: bool) -> fmt::Result {
+        let len = token.chars().count();
+        if !self.empty {
+            let overflow =
+                self.cfg.wrap && self.col + 1 + len > self.cfg.max_width;
+            if force_break || overflow {
+                self.out.write_str(" \\\n")?;
+                indent(self.out, self.cfg, self.depth + 1)?;
+                self.col = (self.depth + 1) * self.cfg.indent.chars().count();
+            } else {
+                self.out.write_char(' ')?;
+                self.col += 1;
+            }
+        }
+        self.out.write_str(token)?;
+        self.col += len;
+        self.empty = false;
+        Ok(())
+    }
+}
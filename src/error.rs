@@ -0,0 +1,305 @@
+//! The parsing error type.
+//!
+//! Errors from Pest are converted here, so that neither Pest nor the grammar
+//! rules show up in the public interface.
+
+use pest::error::{
+    Error as PestErr, ErrorVariant, InputLocation, LineColLocation,
+};
+
+use crate::grammar::Rule;
+use crate::span::{LineIndex, Position, Span};
+use crate::Diagnostic;
+
+use std::ops::Range;
+use std::{fmt, io};
+
+/// The parsing error type.
+///
+/// Every variant but `Io` carries the span of the offending text. More
+/// variants may be added as the parser learns to tell errors apart, so
+/// matching on them needs a wildcard arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The text does not follow the SDLang grammar.
+    Syntax {
+        /// The (empty) span where parsing failed.
+        span: Span,
+        /// Descriptions of the items which would have been valid there (e.g
+        /// `"a value"`).
+        expected: Vec<&'static str>,
+    },
+    /// An escape sequence which does not exist in strings or characters, such
+    /// as `\q`, or a `\u` escape which is not a Unicode scalar value.
+    InvalidEscape {
+        /// The span of the escape sequence, including the backslash.
+        span: Span,
+    },
+    /// A date or time which does not exist, such as `2019/02/30`.
+    InvalidDate {
+        /// The span of the date or time.
+        span: Span,
+    },
+    /// A datetime which is skipped over in its timezone, such as by the start
+    /// of daylight saving time.
+    NonexistentLocalTime {
+        /// The span of the datetime.
+        span: Span,
+    },
+    /// A datetime which is repeated in its timezone (or the default zone, if
+    /// it has none), such as by the end of daylight saving time.
+    AmbiguousLocalTime {
+        /// The span of the datetime.
+        span: Span,
+    },
+    /// A timezone offset of a day or more.
+    InvalidTimezone {
+        /// The span of the offset.
+        span: Span,
+    },
+    /// A timezone name which is not known.
+    UnknownTimezone {
+        /// The span of the name.
+        span: Span,
+    },
+    /// A number which is too large for its type.
+    NumberOutOfRange {
+        /// The span of the number, without its suffix.
+        span: Span,
+        /// The suffix which determines the type: `""`, `"L"` or `"BD"` for
+        /// integers, `"f"` for floats and `"d"` for the days of a duration.
+        suffix: String,
+    },
+    /// A decimal with the `BD` suffix, which needs the `bigdecimal` feature.
+    UnsupportedBigDecimal {
+        /// The span of the decimal, including its suffix.
+        span: Span,
+    },
+    /// Invalid Base64 data.
+    InvalidBase64 {
+        /// The span of the data, including the brackets.
+        span: Span,
+    },
+    /// Tags nested deeper than the parser allows.
+    NestingTooDeep {
+        /// The span of the tag which is too deep, up to its opening brace.
+        span: Span,
+    },
+    /// The text could not be read.
+    Io(io::Error),
+}
+
+impl Error {
+    /// Returns the span of the offending text, if there is one.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::Syntax { span, .. }
+            | Error::InvalidEscape { span }
+            | Error::InvalidDate { span }
+            | Error::NonexistentLocalTime { span }
+            | Error::AmbiguousLocalTime { span }
+            | Error::InvalidTimezone { span }
+            | Error::UnknownTimezone { span }
+            | Error::NumberOutOfRange { span, .. }
+            | Error::UnsupportedBigDecimal { span }
+            | Error::InvalidBase64 { span }
+            | Error::NestingTooDeep { span } => Some(*span),
+            Error::Io(_) => None,
+        }
+    }
+
+    /// Returns a mutable reference to the span, if there is one.
+    pub(crate) fn span_mut(&mut self) -> Option<&mut Span> {
+        match self {
+            Error::Syntax { span, .. }
+            | Error::InvalidEscape { span }
+            | Error::InvalidDate { span }
+            | Error::NonexistentLocalTime { span }
+            | Error::AmbiguousLocalTime { span }
+            | Error::InvalidTimezone { span }
+            | Error::UnknownTimezone { span }
+            | Error::NumberOutOfRange { span, .. }
+            | Error::UnsupportedBigDecimal { span }
+            | Error::InvalidBase64 { span }
+            | Error::NestingTooDeep { span } => Some(span),
+            Error::Io(_) => None,
+        }
+    }
+
+    /// Returns a description of the error, without its position.
+    pub fn message(&self) -> String {
+        match self {
+            Error::Syntax { expected, .. } => match expected.split_last() {
+                None => "Syntax error".to_string(),
+                Some((last, [])) => format!("Expected {}", last),
+                Some((last, rest)) => {
+                    format!("Expected {} or {}", rest.join(", "), last)
+                }
+            },
+            Error::InvalidEscape { .. } => "Invalid escape".to_string(),
+            Error::InvalidDate { .. } => "Invalid date or time".to_string(),
+            Error::NonexistentLocalTime { .. } => {
+                "Datetime does not exist in its timezone".to_string()
+            }
+            Error::AmbiguousLocalTime { .. } => {
+                "Datetime is ambiguous in its timezone".to_string()
+            }
+            Error::InvalidTimezone { .. } => {
+                "Timezone offset out of range".to_string()
+            }
+            Error::UnknownTimezone { .. } => "Unknown timezone".to_string(),
+            Error::NumberOutOfRange { suffix, .. } => match suffix.as_str() {
+                "" => "Number too large for a number without a suffix".into(),
+                "d" => "Number too large for a day count".into(),
+                suffix => format!("Number too large for suffix `{}`", suffix),
+            },
+            Error::UnsupportedBigDecimal { .. } => {
+                "Big decimals are not supported".to_string()
+            }
+            Error::InvalidBase64 { .. } => "Invalid Base64 data".to_string(),
+            Error::NestingTooDeep { .. } => {
+                "Tags nested too deeply".to_string()
+            }
+            Error::Io(err) => format!("I/O error: {}", err),
+        }
+    }
+
+    /// Prepares a diagnostic for displaying the error, given the text which
+    /// was parsed.
+    pub fn diagnostic<'a>(&'a self, text: &'a str) -> Diagnostic<'a> {
+        Diagnostic::new(self, text)
+    }
+}
+
+impl fmt::Display for Error {
+    /// Formats as the message followed by the position (if any).
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.span() {
+            Some(span) => write!(f, "{} at {}", self.message(), span.start),
+            None => f.write_str(&self.message()),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// The parsing result type, based on `Error`.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Converts a Pest error into a syntax error.
+pub fn syntax(err: PestErr<Rule>) -> Error {
+    let (offset, (line, col)) = match (err.location, err.line_col) {
+        (InputLocation::Pos(offset), LineColLocation::Pos(pos)) => {
+            (offset, pos)
+        }
+        (InputLocation::Span((offset, _)), LineColLocation::Span(pos, _)) => {
+            (offset, pos)
+        }
+        _ => unreachable!(),
+    };
+    let pos = Position { offset, line, col };
+    let mut expected = match err.variant {
+        ErrorVariant::ParsingError { positives, .. } => {
+            positives.into_iter().map(describe).collect()
+        }
+        ErrorVariant::CustomError { .. } => Vec::new(),
+    };
+    expected.sort_unstable();
+    expected.dedup();
+    Error::Syntax {
+        span: Span {
+            start: pos,
+            end: pos,
+        },
+        expected,
+    }
+}
+
+/// Returns a plain-English description of a grammar rule.
+pub fn describe(rule: Rule) -> &'static str {
+    match rule {
+        Rule::string => "a string",
+        Rule::character => "a character",
+        Rule::date => "a date",
+        Rule::fraction => "a fraction of a second",
+        Rule::time => "a time",
+        Rule::tz_offset => "a timezone offset",
+        Rule::tz_name | Rule::tz_name_char => "a timezone name",
+        Rule::timezone => "a timezone",
+        Rule::datetime => "a datetime",
+        Rule::days => "a day count",
+        Rule::duration => "a duration",
+        Rule::number_raw | Rule::number => "a number",
+        Rule::number_suffix => "a number suffix",
+        Rule::decimal_raw | Rule::decimal => "a decimal",
+        Rule::decimal_suffix => "a decimal suffix",
+        Rule::bool_true | Rule::bool_false | Rule::boolean => "a boolean",
+        Rule::null => "null",
+        Rule::base64_char | Rule::base64 => "Base64 data",
+        Rule::value => "a value",
+        Rule::ident => "an identifier",
+        Rule::attribute => "an attribute",
+        Rule::namespace => "a namespace",
+        Rule::tag_head | Rule::tag | Rule::tag_start | Rule::tags => "a tag",
+        Rule::open_brace => "`{`",
+        Rule::tagtree => "a document",
+        Rule::tags_sep => "a newline or `;`",
+        Rule::WHITESPACE | Rule::white | Rule::line_white => "whitespace",
+        Rule::COMMENT => "a comment",
+        Rule::ident_char => "an identifier",
+        Rule::EOI => "the end of the text",
+    }
+}
+
+/// Converts a byte range of the text of a Pest span into a span.
+pub fn locate_within(span: pest::Span, range: Range<usize>) -> Span {
+    let start = locate(span.clone()).start;
+    let lines = LineIndex::new(span.as_str());
+    // Positions within the text are relative to its start.
+    let position = |offset| {
+        let pos = lines.position(offset);
+        Position {
+            offset: start.offset + offset,
+            line: start.line + pos.line - 1,
+            col: if pos.line == 1 {
+                start.col + pos.col - 1
+            } else {
+                pos.col
+            },
+        }
+    };
+    Span {
+        start: position(range.start),
+        end: position(range.end),
+    }
+}
+
+/// Converts a Pest span into a span.
+pub fn locate(span: pest::Span) -> Span {
+    let position = |pos: pest::Position| {
+        let (line, col) = pos.line_col();
+        Position {
+            offset: pos.pos(),
+            line,
+            col,
+        }
+    };
+    Span {
+        start: position(span.start_pos()),
+        end: position(span.end_pos()),
+    }
+}
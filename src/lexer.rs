@@ -0,0 +1,426 @@
+//! A hand-written tokenizer, for the recursive-descent parser.
+//!
+//! What a piece of text means depends on where it is (e.g `true` is a value,
+//! but the start of an identifier in `trueish`), so rather than splitting the
+//! whole text up front, the lexer recognizes one token at a time, of the kind
+//! asked for by the parser. Tokens are recognized exactly as by the rules of
+//! `grammar.pest`, trying alternatives in the same order.
+
+use crate::recover::char_len;
+
+/// A value, split up into the parts of its text which are needed to convert
+/// it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Token<'a> {
+    /// A string, without its quotes.
+    String {
+        /// The text between the quotes, with its escapes.
+        text: &'a str,
+        /// Whether it is a raw string.
+        raw: bool,
+    },
+    /// A character, without its quotes, which may be an escape.
+    Char(&'a str),
+    /// Base64 data, without its brackets.
+    Base64(&'a str),
+    /// A date.
+    Date(&'a str),
+    /// A date and time, with a timezone if any.
+    DateTime {
+        date: &'a str,
+        time: &'a str,
+        zone: Option<Zone<'a>>,
+    },
+    /// A duration, with a day count if any.
+    Duration {
+        negative: bool,
+        days: Option<&'a str>,
+        time: &'a str,
+    },
+    /// A decimal, with its suffix (if any).
+    Decimal { digits: &'a str, suffix: &'a str },
+    /// A number, with its suffix (if any).
+    Number { digits: &'a str, suffix: &'a str },
+    /// A boolean.
+    Boolean(bool),
+    /// Null.
+    Null,
+}
+
+/// The timezone of a datetime.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Zone<'a> {
+    /// `UTC`.
+    Utc,
+    /// `GMT`, with its offset (such as `+01:00`) if any.
+    Gmt(Option<&'a str>),
+    /// A timezone name.
+    Named(&'a str),
+}
+
+/// A position in the text, from which tokens are read.
+pub struct Lexer<'a> {
+    text: &'a str,
+    /// The byte offset of the rest of the text.
+    pub pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Lexer { text, pos: 0 }
+    }
+
+    /// Returns the next byte, if any.
+    pub fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    /// Skips the given byte, if it is next.
+    pub fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Skips the given text, if it is next.
+    fn eat_str(&mut self, text: &str) -> bool {
+        let found = self.text[self.pos..].starts_with(text);
+        if found {
+            self.pos += text.len();
+        }
+        found
+    }
+
+    /// Skips the given number of digits, if they are next.
+    fn eat_digits(&mut self, count: usize) -> bool {
+        let found = self.text.as_bytes()[self.pos..]
+            .get(..count)
+            .is_some_and(|digits| digits.iter().all(u8::is_ascii_digit));
+        if found {
+            self.pos += count;
+        }
+        found
+    }
+
+    /// Skips any number of digits, returning how many there were.
+    fn digits(&mut self) -> usize {
+        let count = self.text.as_bytes()[self.pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        self.pos += count;
+        count
+    }
+
+    /// Returns the text from the given offset up to the current one.
+    fn since(&self, start: usize) -> &'a str {
+        &self.text[start..self.pos]
+    }
+
+    /// Skips whitespace and comments which do not end the line, returning
+    /// whether there were any.
+    pub fn line_white(&mut self) -> bool {
+        let start = self.pos;
+        while self.white_item(false) {}
+        self.pos > start
+    }
+
+    /// Skips whitespace (including newlines) and comments.
+    pub fn white(&mut self) {
+        while self.white_item(true) {}
+    }
+
+    /// Skips a whitespace character (a newline only if `newlines`), a line
+    /// continuation or a comment, returning whether there was one.
+    fn white_item(&mut self, newlines: bool) -> bool {
+        let rest = &self.text[self.pos..];
+        let len = match self.peek() {
+            Some(b' ') | Some(b'\t') | Some(b'\r') => 1,
+            Some(b'\n') if newlines => 1,
+            Some(b'\\') if rest.starts_with("\\\n") => 2,
+            Some(b'/') if rest.starts_with("/*") => {
+                match rest[2..].find("*/") {
+                    Some(end) => end + 4,
+                    None => return false,
+                }
+            }
+            Some(b'/') | Some(b'-') | Some(b'#')
+                if rest.starts_with("//")
+                    || rest.starts_with("--")
+                    || rest.starts_with('#') =>
+            {
+                rest.find('\n').unwrap_or(rest.len())
+            }
+            _ => return false,
+        };
+        self.pos += len;
+        true
+    }
+
+    /// Reads an identifier.
+    pub fn ident(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        match self.peek() {
+            Some(b) if b.is_ascii_alphabetic() || b == b'_' => self.pos += 1,
+            _ => return None,
+        }
+        while self.peek().is_some_and(is_ident_char) {
+            self.pos += 1;
+        }
+        Some(self.since(start))
+    }
+
+    /// Reads a value, leaving the position unchanged if there is none.
+    pub fn value(&mut self) -> Option<Token<'a>> {
+        let start = self.pos;
+        let token = match self.peek()? {
+            b'"' => self.string(),
+            b'`' => self.raw_string(),
+            b'\'' => self.character(),
+            b'[' => self.base64(),
+            b'0'..=b'9' | b'-' => self.numeric(),
+            _ => self.keyword(),
+        };
+        if token.is_none() {
+            self.pos = start;
+        }
+        token
+    }
+
+    fn string(&mut self) -> Option<Token<'a>> {
+        self.pos += 1;
+        let start = self.pos;
+        loop {
+            match self.peek()? {
+                // Other bytes of an escaped character are never special.
+                b'\\' => self.pos += 2,
+                b'"' => break,
+                _ => self.pos += 1,
+            }
+        }
+        let text = self.since(start);
+        self.pos += 1;
+        Some(Token::String { text, raw: false })
+    }
+
+    fn raw_string(&mut self) -> Option<Token<'a>> {
+        let start = self.pos + 1;
+        let end = start + self.text[start..].find('`')?;
+        self.pos = end + 1;
+        Some(Token::String {
+            text: &self.text[start..end],
+            raw: true,
+        })
+    }
+
+    fn character(&mut self) -> Option<Token<'a>> {
+        let start = self.pos + 1;
+        let len = char_len(&self.text[start..])?;
+        self.pos = start + len;
+        Some(Token::Char(&self.text[start..self.pos - 1]))
+    }
+
+    fn base64(&mut self) -> Option<Token<'a>> {
+        self.pos += 1;
+        let start = self.pos;
+        loop {
+            match self.peek()? {
+                b']' => break,
+                b if is_base64_char(b) => self.pos += 1,
+                b' ' | b'\n' | b'\r' | b'\t' => self.pos += 1,
+                b'\\' if self.eat_str("\\\n") => {}
+                _ => return None,
+            }
+        }
+        let text = self.since(start);
+        self.pos += 1;
+        Some(Token::Base64(text))
+    }
+
+    /// Reads a datetime, date, duration, decimal or number.
+    fn numeric(&mut self) -> Option<Token<'a>> {
+        let start = self.pos;
+        if let Some(date) = self.date() {
+            // A date followed by a time on the same line is a datetime.
+            let end = self.pos;
+            if self.line_white() {
+                if let Some(time) = self.time() {
+                    let zone = self.timezone();
+                    return Some(Token::DateTime { date, time, zone });
+                }
+            }
+            self.pos = end;
+            return Some(Token::Date(date));
+        }
+        if let Some(token) = self.duration() {
+            return Some(token);
+        }
+
+        self.pos = start;
+        self.eat(b'-');
+        if self.digits() == 0 {
+            return None;
+        }
+        let end = self.pos;
+        if self.eat(b'.') && self.digits() > 0 {
+            let digits = self.since(start);
+            let suffix = if self.eat(b'f') {
+                "f"
+            } else if self.eat(b'd') {
+                "d"
+            } else if self.eat_str("BD") {
+                "BD"
+            } else {
+                ""
+            };
+            return Some(Token::Decimal { digits, suffix });
+        }
+        self.pos = end;
+        let digits = self.since(start);
+        let suffix = if self.eat(b'L') {
+            "L"
+        } else if self.eat_str("BD") {
+            "BD"
+        } else {
+            ""
+        };
+        Some(Token::Number { digits, suffix })
+    }
+
+    fn date(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        if self.eat_digits(4)
+            && self.eat(b'/')
+            && self.eat_digits(2)
+            && self.eat(b'/')
+            && self.eat_digits(2)
+        {
+            return Some(self.since(start));
+        }
+        self.pos = start;
+        None
+    }
+
+    /// Reads a time, whose seconds and fraction of a second are optional.
+    fn time(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        if !(self.eat_digits(2) && self.eat(b':') && self.eat_digits(2)) {
+            self.pos = start;
+            return None;
+        }
+        let end = self.pos;
+        if !(self.eat(b':') && self.eat_digits(2)) {
+            self.pos = end;
+            return Some(self.since(start));
+        }
+        let end = self.pos;
+        if !(self.eat(b'.') && self.fraction_digits() > 0) {
+            self.pos = end;
+        }
+        Some(self.since(start))
+    }
+
+    /// Skips up to nine digits of a fraction of a second, returning how many
+    /// there were.
+    fn fraction_digits(&mut self) -> usize {
+        let count = self.text.as_bytes()[self.pos..]
+            .iter()
+            .take(9)
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        self.pos += count;
+        count
+    }
+
+    /// Reads the timezone of a datetime.
+    fn timezone(&mut self) -> Option<Zone<'a>> {
+        let start = self.pos;
+        if !self.eat(b'-') {
+            return None;
+        }
+        let zone = if self.eat_str("UTC") {
+            Some(Zone::Utc)
+        } else if self.eat_str("GMT") {
+            let offset = self.pos;
+            if (self.eat(b'+') || self.eat(b'-')) && self.eat_digits(2) {
+                let end = self.pos;
+                if !(self.eat(b':') && self.eat_digits(2)) {
+                    self.pos = end;
+                }
+            } else {
+                self.pos = offset;
+            }
+            Some(Zone::Gmt(
+                Some(self.since(offset)).filter(|o| !o.is_empty()),
+            ))
+        } else {
+            None
+        };
+        // Otherwise, they are the start of a name (e.g `GMT+5` or `UTCx`).
+        if zone.is_some() && !self.peek().is_some_and(is_zone_char) {
+            return zone;
+        }
+
+        self.pos = start + 1;
+        if !self.peek().is_some_and(|b| b.is_ascii_alphabetic()) {
+            self.pos = start;
+            return None;
+        }
+        while self.peek().is_some_and(is_zone_char) {
+            self.pos += 1;
+        }
+        Some(Zone::Named(self.since(start + 1)))
+    }
+
+    fn duration(&mut self) -> Option<Token<'a>> {
+        let negative = self.eat(b'-');
+        let start = self.pos;
+        let days = if self.digits() > 0 && self.eat_str("d:") {
+            Some(&self.text[start..self.pos - 2])
+        } else {
+            self.pos = start;
+            None
+        };
+        let time = self.time()?;
+        Some(Token::Duration {
+            negative,
+            days,
+            time,
+        })
+    }
+
+    /// Reads a boolean or null.
+    fn keyword(&mut self) -> Option<Token<'a>> {
+        let token = if self.eat_str("true") || self.eat_str("on") {
+            Token::Boolean(true)
+        } else if self.eat_str("false") || self.eat_str("off") {
+            Token::Boolean(false)
+        } else if self.eat_str("null") {
+            Token::Null
+        } else {
+            return None;
+        };
+        // Keywords must not be the prefix of an identifier (e.g `only`).
+        if self.peek().is_some_and(is_ident_char) {
+            return None;
+        }
+        Some(token)
+    }
+}
+
+/// Whether the byte may be part of an identifier.
+fn is_ident_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'.' | b'$' | b'-' | b'_')
+}
+
+/// Whether the byte is a Base64 character.
+pub fn is_base64_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'=')
+}
+
+/// Whether the byte may be part of a timezone name (after its first letter).
+fn is_zone_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'/' | b'_' | b'+' | b'-')
+}
@@ -43,11 +43,83 @@
 //! // Prints `tag hello_world: "text"`
 //! println!("{}", sdlang::parse_text("hello_world \"text\"").unwrap());
 //! ```
+//!
+//! Tags can be written back out just as easily:
+//! ```rust
+//! extern crate sdlang;
+//!
+//! let root = sdlang::parse_text("hello_world \"text\"").unwrap();
+//! assert_eq!(
+//!     sdlang::emit_text(&root).unwrap(),
+//!     "hello_world \"text\"\n"
+//! );
+//! ```
+//! When parsing many small documents, `parse_text_borrowed` saves on
+//! allocations by borrowing identifiers and strings from the text.
+//!
+//! Large documents can be parsed piece by piece with `parse_events`, which
+//! reads them as a series of events rather than building a tree.
+//!
+//! To report errors in the contents of a document, `parse_text_with_spans`
+//! also returns the line and column of every tag, value and attribute.
+//!
+//! To edit a document without losing its comments and layout, parse it into
+//! a `Document` instead: a lossless syntax tree (see the `cst` module) that
+//! writes back out exactly as it was read. Its values, attributes and child
+//! tags can be changed in place, touching as little of the text as possible:
+//! ```rust
+//! use sdlang::{Document, Value};
+//!
+//! let mut doc = "// Release\nversion 1 2 // major, minor\n"
+//!     .parse::<Document>()
+//!     .unwrap();
+//! doc.set_value(&["version"], 1, &Value::from(3)).unwrap();
+//! assert_eq!(doc.to_string(), "// Release\nversion 1 3 // major, minor\n");
+//! ```
+//!
+//! The layout of the output can be configured by passing a `PrettyConfig` to
+//! `emit_text_with`.
+//!
+//! With the `serde` feature enabled, Rust data structures can be converted to
+//! and from SDLang documents directly through `from_str` and `to_string`. The
+//! mapping between the two is described in the `de` module. `Tag`,
+//! `Attribute` and `Value` themselves then implement `Serialize` and
+//! `Deserialize` as well, for storing parsed trees in other formats.
+//!
 //! Note that all SDLang-related types (i.e `Tag`, `Attribute` and `Value`)
 //! implement `FromStr` so that they can be used with `str::parse`. Note,
 //! however, that in order to parse a whole file, which may have multiple root
 //! tags, use `parse_text` or `parse_file`.
 //!
+//! Parsing failures are reported as an `Error`, which tells apart the kinds of
+//! failure and locates them in the text:
+//! ```rust
+//! extern crate sdlang;
+//!
+//! match sdlang::parse_text("big 3000000000") {
+//!     Err(sdlang::Error::NumberOutOfRange { span, suffix }) => {
+//!         assert_eq!((span.start.line, span.start.col), (1, 5));
+//!         assert_eq!(suffix, "");
+//!     }
+//!     res => panic!("unexpected result: {:?}", res),
+//! }
+//! ```
+//! To show errors to users, `Error::diagnostic` renders them along with the
+//! offending line of the text, plainly or in color.
+//!
+//! Datetimes may be given in `UTC`, at a `GMT` offset, or in a named timezone,
+//! which is kept alongside the datetime as a `Timezone` so that it is written
+//! back the same way. Common abbreviations such as `JST` are always known;
+//! the `tz` feature adds the names of the IANA timezone database (e.g
+//! `America/Los_Angeles`), whose offsets follow daylight saving time.
+//! Datetimes without a timezone are kept as `Value::NaiveDateTime`, unless a
+//! `DefaultZone` (such as the local timezone of the machine) is given in the
+//! `ParseOptions` passed to `parse_text_with`.
+//!
+//! For debugging, the `log` feature makes the parser report every tag, value
+//! and attribute it parses, along with its span, as `trace`-level events of
+//! the `log` crate (with the target `sdlang::parse`).
+//!
 //! [sdlang]: https://sdlang.org "Official SDLang Website"
 
 // Crates
@@ -59,40 +131,282 @@ extern crate pest;
 extern crate pest_derive;
 
 // Modules
+mod borrowed;
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "bigdecimal")]
+mod decimal;
+#[cfg(feature = "handwritten")]
+mod descent;
+mod diagnostic;
+mod edit;
+mod emit;
+mod error;
 mod grammar;
+#[cfg(feature = "handwritten")]
+mod lexer;
+mod options;
 mod parse;
+mod pretty;
+mod recover;
+#[cfg(feature = "serde")]
+pub mod ser;
+mod span;
+mod stream;
 #[cfg(test)]
 mod tests;
 mod types;
+mod zone;
 
 // Public types
-pub use grammar::{Error, ParseRes as Result};
-pub use types::{Attribute, Date, DateTime, Tag, Value};
+pub use borrowed::{AttributeRef, TagRef, ValueRef};
+pub use cst::Document;
+#[cfg(feature = "bigdecimal")]
+pub use decimal::{BigDecimal, ParseBigDecimalError};
+pub use diagnostic::Diagnostic;
+pub use edit::EditError;
+pub use error::{Error, Result};
+pub use options::{DefaultZone, ParseOptions};
+pub use pretty::{BraceStyle, PrettyConfig};
+pub use span::{AttrSpans, Position, Span, TagSpans};
+pub use stream::{Event, Events};
+#[cfg(feature = "serde")]
+pub use {
+    de::{from_str, from_tag},
+    ser::{to_string, to_tag},
+};
+pub use types::{Attribute, Date, DateTime, Duration, NaiveDateTime};
+pub use types::{DecimalKind, NumberKind, Tag, Timezone, Value};
 
 // Internal usage here
-use std::{io, io::Read};
+use std::{fmt, io, io::Read};
 
 /// Reads everything from the given Reader and parses it.
 ///
 /// Look at `parse_text` for more information.
 ///
-/// The reader is internally buffered using `std::io::BufReader`.
-pub fn parse_file<R>(data: R) -> io::Result<Result<Tag>>
+/// The reader is internally buffered using `std::io::BufReader`. Errors in
+/// reading are reported as `Error::Io`.
+pub fn parse_file<R>(data: R) -> Result<Tag>
+where
+    R: io::Read,
+{
+    parse_file_with(data, &ParseOptions::default())
+}
+
+/// Reads everything from the given Reader and parses it with the given
+/// options.
+///
+/// Look at `parse_file` for more information.
+pub fn parse_file_with<R>(data: R, options: &ParseOptions) -> Result<Tag>
 where
     R: io::Read,
 {
     let mut res = String::new();
     io::BufReader::new(data).read_to_string(&mut res)?;
-    Ok(parse_text(res.as_str()))
+    parse_text_with(res.as_str(), options)
+}
+
+/// Parses the given Reader incrementally, as a series of events.
+///
+/// Unlike `parse_file`, this never holds the whole text or tree in memory;
+/// look at `Events` for more information.
+pub fn parse_events<R>(data: R) -> Events<R>
+where
+    R: io::Read,
+{
+    Events::new(data)
+}
+
+/// Parses the given Reader incrementally with the given options, as a series
+/// of events.
+///
+/// Look at `parse_events` for more information.
+pub fn parse_events_with<R>(data: R, options: &ParseOptions) -> Events<R>
+where
+    R: io::Read,
+{
+    Events::with_options(data, options)
 }
 
 /// Parses the given text into a root tag.
 ///
 /// The name of the root tag is `""` (nothing); It has no namespace, values, or
 /// attributes; it only has a list of child tags.
+///
+/// With the `handwritten` feature, the text is parsed by a faster, hand-written
+/// parser, falling back on the Pest grammar to report errors.
 pub fn parse_text(data: &str) -> Result<Tag> {
+    parse_text_with(data, &ParseOptions::default())
+}
+
+/// Parses the given text into a root tag with the given options.
+///
+/// Look at `parse_text` for more information.
+pub fn parse_text_with(data: &str, options: &ParseOptions) -> Result<Tag> {
+    #[cfg(feature = "handwritten")]
+    if let Some(tags) = descent::tagtree(data, options) {
+        return Ok(Tag::new(String::new()).tags(tags));
+    }
+    parse_text_pest(data, options)
+}
+
+/// Parses the given text into a root tag with the Pest grammar, whatever the
+/// features.
+///
+/// This is only public to let the benchmarks compare both parsers.
+#[doc(hidden)]
+pub fn parse_text_pest(data: &str, options: &ParseOptions) -> Result<Tag> {
     Ok(Tag::new(String::new()).tags(
         grammar::parse(grammar::Rule::tagtree, data)
-            .and_then(parse::tagtree)?,
+            .and_then(|tree| parse::tagtree(tree, options))?,
     ))
 }
+
+/// Parses the given text into a root tag, borrowing from the text where
+/// possible.
+///
+/// Identifiers and strings without escapes are not copied, which saves
+/// allocations when parsing many small documents. The result can be converted
+/// into the tag returned by `parse_text` with `TagRef::into_owned`.
+pub fn parse_text_borrowed(data: &str) -> Result<TagRef<'_>> {
+    parse_text_borrowed_with(data, &ParseOptions::default())
+}
+
+/// Parses the given text into a root tag with the given options, borrowing
+/// from the text where possible.
+///
+/// Look at `parse_text_borrowed` for more information.
+pub fn parse_text_borrowed_with<'a>(
+    data: &'a str,
+    options: &ParseOptions,
+) -> Result<TagRef<'a>> {
+    let mut root = TagRef::new("".into());
+    root.tags = grammar::parse(grammar::Rule::tagtree, data)
+        .and_then(|tree| parse::tagtree_ref(tree, options))?;
+    Ok(root)
+}
+
+/// Parses the given text into a root tag, recovering from errors.
+///
+/// Rather than stopping at the first error, parsing skips the broken tag up to
+/// the next `;` or newline (along with its child tags, if it has any) and
+/// carries on, so that every error in the text is reported. Broken tags are
+/// left out of the returned tree.
+///
+/// Without errors, the returned tag is the same as that of `parse_text`.
+/// ```rust
+/// let (root, errors) = sdlang::parse_text_recovering("a 1\nb 2019/13/01\nc");
+/// assert_eq!(root.tags.len(), 2);
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].span().unwrap().start.line, 2);
+/// ```
+pub fn parse_text_recovering(data: &str) -> (Tag, Vec<Error>) {
+    parse_text_recovering_with(data, &ParseOptions::default())
+}
+
+/// Parses the given text into a root tag with the given options, recovering
+/// from errors.
+///
+/// Look at `parse_text_recovering` for more information.
+pub fn parse_text_recovering_with(
+    data: &str,
+    options: &ParseOptions,
+) -> (Tag, Vec<Error>) {
+    let mut recovery = recover::Recovery::new(data, options);
+    let tags = recovery.tags(false);
+    (Tag::new(String::new()).tags(tags), recovery.errors)
+}
+
+/// Parses the given text into a root tag, recording the source spans of
+/// everything in it.
+///
+/// The returned spans mirror the structure of the returned tag; look at
+/// `TagSpans` for more information. The span of the root tag covers the whole
+/// text.
+///
+/// Otherwise, this is the same as `parse_text`.
+pub fn parse_text_with_spans(data: &str) -> Result<(Tag, TagSpans)> {
+    parse_text_with_spans_with(data, &ParseOptions::default())
+}
+
+/// Parses the given text into a root tag with the given options, recording
+/// the source spans of everything in it.
+///
+/// Look at `parse_text_with_spans` for more information.
+pub fn parse_text_with_spans_with(
+    data: &str,
+    options: &ParseOptions,
+) -> Result<(Tag, TagSpans)> {
+    let lines = span::LineIndex::new(data);
+    let (tags, spans) = grammar::parse(grammar::Rule::tagtree, data)
+        .and_then(|tree| parse::tagtree_spanned(tree, &lines, options))?;
+
+    let mut root = TagSpans::new(Span {
+        start: lines.position(0),
+        end: lines.position(data.len()),
+    });
+    root.tags = spans;
+    Ok((Tag::new(String::new()).tags(tags), root))
+}
+
+/// Writes the given root tag out as SDLang text into the given Writer.
+///
+/// Look at `emit_text` for more information.
+///
+/// Unrepresentable tags are reported as `io::ErrorKind::InvalidData`.
+pub fn emit_file<W>(tag: &Tag, out: W) -> io::Result<()>
+where
+    W: io::Write,
+{
+    emit_file_with(tag, &PrettyConfig::default(), out)
+}
+
+/// Writes the given root tag out as SDLang text into the given Writer, laid
+/// out according to the given configuration.
+///
+/// Look at `emit_text` for more information.
+pub fn emit_file_with<W>(
+    tag: &Tag,
+    config: &PrettyConfig,
+    mut out: W,
+) -> io::Result<()>
+where
+    W: io::Write,
+{
+    let text = emit_text_with(tag, config).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "tag cannot be represented in SDLang",
+        )
+    })?;
+    out.write_all(text.as_bytes())
+}
+
+/// Writes the given root tag out as SDLang text.
+///
+/// This is the inverse of `parse_text`: only the child tags of the root tag
+/// are written, laid out according to the default `PrettyConfig`.
+///
+/// Fails if any part of the tree cannot be represented in SDLang: invalid or
+/// keyword identifiers, anonymous tags without values, non-finite decimals,
+/// or dates outside of years 0 to 9999. Datetimes and durations are written
+/// with their seconds, and to the nanosecond, so `12:00` is written back as
+/// `12:00:00` and `12:00:00.5` as `12:00:00.500`.
+pub fn emit_text(tag: &Tag) -> std::result::Result<String, fmt::Error> {
+    emit_text_with(tag, &PrettyConfig::default())
+}
+
+/// Writes the given root tag out as SDLang text, laid out according to the
+/// given configuration.
+///
+/// Look at `emit_text` for more information.
+pub fn emit_text_with(
+    tag: &Tag,
+    config: &PrettyConfig,
+) -> std::result::Result<String, fmt::Error> {
+    let mut res = String::new();
+    emit::tags(&mut res, &tag.tags, config, 0)?;
+    Ok(res)
+}
@@ -0,0 +1,72 @@
+//! Parsing options.
+//!
+//! The options only affect how values are interpreted; the syntax accepted by
+//! the parser is always the same.
+
+use chrono::FixedOffset;
+
+/// The timezone of datetimes written without one.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DefaultZone {
+    /// No timezone: such datetimes are kept as `Value::NaiveDateTime`, for
+    /// the user to interpret.
+    Naive,
+    /// The local timezone of the machine, which depends on its settings (e.g
+    /// the `TZ` environment variable).
+    ///
+    /// Local times which are skipped over or repeated (e.g by the start or end
+    /// of daylight saving time) are errors.
+    Local,
+    /// `UTC`.
+    Utc,
+    /// A fixed offset from `UTC`.
+    Fixed(FixedOffset),
+}
+
+/// Options for parsing text.
+///
+/// The default options are the ones used by `parse_text`: datetimes without a
+/// timezone are kept as `Value::NaiveDateTime`.
+///
+/// Like `PrettyConfig`, it can be built up through chained method calls:
+/// ```rust
+/// use sdlang::{DefaultZone, ParseOptions};
+///
+/// let options = ParseOptions::new().default_zone(DefaultZone::Utc);
+/// let root = sdlang::parse_text_with("a 2019/01/02 03:04:05", &options)
+///     .unwrap();
+/// assert_eq!(
+///     root.tags[0].values[0].to_string(),
+///     "2019-01-02 03:04:05 +00:00"
+/// );
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseOptions {
+    /// The timezone of datetimes written without one.
+    ///
+    /// Unless it is `DefaultZone::Naive`, such datetimes are resolved to a
+    /// `Value::DateTime` with `Timezone::Local`, so that they are written back
+    /// without a timezone.
+    pub default_zone: DefaultZone,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            default_zone: DefaultZone::Naive,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the timezone of datetimes written without one.
+    pub fn default_zone(mut self, zone: DefaultZone) -> Self {
+        self.default_zone = zone;
+        self
+    }
+}
@@ -5,13 +5,24 @@
 
 use base64 as b64;
 
-use chrono::{Local, Offset, TimeZone, Utc};
+use chrono::{FixedOffset, Local, LocalResult, TimeZone, Utc};
 use chrono::{NaiveDateTime, NaiveTime, Timelike};
 
-use crate::grammar::{parse_err, ParseTree, Rule};
-use crate::{Attribute, Date, DateTime, Result, Tag, Value};
-
-use std::time::Duration;
+#[cfg(feature = "bigdecimal")]
+use crate::decimal::BigDecimal;
+#[cfg(feature = "log")]
+use crate::error::describe;
+use crate::error::{locate, locate_within};
+use crate::grammar::{ParseTree, Rule};
+use crate::span::{AttrSpans, LineIndex, Span, TagSpans};
+use crate::zone::Zone;
+use crate::{Attribute, Date, DateTime, Duration, Error, Result, Tag};
+use crate::{NumberKind, Timezone, Value};
+use crate::{DefaultZone, ParseOptions};
+use crate::{AttributeRef, TagRef, ValueRef};
+
+use std::borrow::Cow;
+use std::ops::Range;
 
 pub fn string(tree: ParseTree) -> Result<String> {
     // Get positional info
@@ -23,141 +34,326 @@ pub fn string(tree: ParseTree) -> Result<String> {
     let text = &tree.as_str()[1..=len];
 
     // Check if its a raw string. If so, then we don't parse escapes, so exit.
-    if tree.as_str().chars().next().unwrap() == '`' {
+    if tree.as_str().starts_with('`') {
         return Ok(text.to_string());
     }
 
-    // Iterate, parsing escapes.
-    Ok(text
-        .chars()
-        .fold((false, String::with_capacity(len)), |(esc, mut res), ch| {
-            if esc {
-                // Parsing an escape.
-                res.push(match ch {
-                    'n' => '\n',
-                    'r' => '\r',
-                    't' => '\t',
-                    '0' => '\x00',
-                    c => c,
-                });
-                (false, res)
-            } else if ch == '\\' {
-                // It's an escape. Don't add it to the string.
-                (true, res)
-            } else {
+    unescape(text).map_err(|range| Error::InvalidEscape {
+        // The range is relative to the text after the quote.
+        span: locate_within(span, range.start + 1..range.end + 1),
+    })
+}
+
+/// Replaces the escapes in the text of a string or character.
+///
+/// A backslash at the end of a line continues the text on the next line,
+/// leaving out the line break and the indentation of the next line. On an
+/// invalid escape, returns its byte range in the text.
+pub fn unescape(text: &str) -> std::result::Result<String, Range<usize>> {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('\\') {
+        res.push_str(&rest[..idx]);
+        let start = text.len() - rest.len() + idx;
+        rest = &rest[idx + 1..];
+        let ch = rest.chars().next().ok_or(start..text.len())?;
+        rest = &rest[ch.len_utf8()..];
+        match ch {
+            'n' => res.push('\n'),
+            'r' => res.push('\r'),
+            't' => res.push('\t'),
+            '0' => res.push('\x00'),
+            '\\' | '"' | '\'' => res.push(ch),
+            '\n' => rest = rest.trim_start_matches(&[' ', '\t'][..]),
+            '\r' if rest.starts_with('\n') => {
+                rest = rest[1..].trim_start_matches(&[' ', '\t'][..])
+            }
+            'u' => {
+                let (ch, len) =
+                    unicode(rest).map_err(|len| start..start + 2 + len)?;
                 res.push(ch);
-                (false, res)
+                rest = &rest[len..];
             }
-        })
-        .1)
+            _ => return Err(start..start + 1 + ch.len_utf8()),
+        }
+    }
+    res.push_str(rest);
+    Ok(res)
+}
+
+/// Reads the digits of a `\u` escape (and those of the low surrogate, after
+/// a high one), returning the character and the length of the text read.
+///
+/// On error, returns the length of the digits in error: none if there are not
+/// four of them, or those of a surrogate which is not part of a pair.
+fn unicode(text: &str) -> std::result::Result<(char, usize), usize> {
+    let hex = |text: &str| {
+        text.get(..4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .map(|digits| u32::from_str_radix(digits, 16).unwrap())
+    };
+    let high = hex(text).ok_or(0usize)?;
+    if let Some(ch) = std::char::from_u32(high) {
+        return Ok((ch, 4));
+    }
+    let low = text[4..]
+        .strip_prefix("\\u")
+        .and_then(hex)
+        .filter(|low| (0xDC00..0xE000).contains(low));
+    match low {
+        Some(low) if high < 0xDC00 => {
+            let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+            Ok((std::char::from_u32(code).unwrap(), 10))
+        }
+        _ => Err(4),
+    }
+}
+
+pub fn character(tree: ParseTree) -> Result<char> {
+    let span = tree.as_span();
+    let text = &tree.as_str()[1..span.end() - span.start() - 1];
+    let invalid = |range: Range<usize>| Error::InvalidEscape {
+        // The range is relative to the text after the quote.
+        span: locate_within(span.clone(), range.start + 1..range.end + 1),
+    };
+    // The grammar only allows a single character, escaped or not, but a line
+    // continuation leaves none.
+    unescape(text)
+        .map_err(invalid)?
+        .chars()
+        .next()
+        .ok_or_else(|| invalid(0..text.len()))
 }
 
 pub fn date(tree: ParseTree) -> Result<Date> {
-    Date::parse_from_str(tree.as_str(), "%Y/%m/%d").map_err(|_| {
-        parse_err(
-            format!("Error in parsing '{}' into a date!", tree.as_str()),
-            tree.as_span(),
-        )
+    date_str(tree.as_str()).ok_or_else(|| Error::InvalidDate {
+        span: locate(tree.as_span()),
     })
 }
 
+/// Converts the text of a date.
+pub fn date_str(text: &str) -> Option<Date> {
+    Date::parse_from_str(text, "%Y/%m/%d").ok()
+}
+
 pub fn time(tree: ParseTree) -> Result<NaiveTime> {
-    let text = tree.as_str();
-    let span = tree.as_span();
-    let fmt = if tree.into_inner().next().is_some() {
-        "%H:%M:%S%.3f"
-    } else {
+    time_str(tree.as_str()).ok_or_else(|| Error::InvalidDate {
+        span: locate(tree.as_span()),
+    })
+}
+
+/// Converts the text of a time, whose seconds and fraction of a second are
+/// optional.
+pub fn time_str(text: &str) -> Option<NaiveTime> {
+    let fmt = if text.contains('.') {
+        "%H:%M:%S%.f"
+    } else if text.len() > 5 {
         "%H:%M:%S"
+    } else {
+        "%H:%M"
     };
 
-    NaiveTime::parse_from_str(text, fmt).map_err(|_| {
-        parse_err(format!("Error in parsing '{}' into a time!", text), span)
+    NaiveTime::parse_from_str(text, fmt).ok()
+}
+
+pub fn tz_offset(tree: ParseTree) -> Result<FixedOffset> {
+    tz_offset_str(tree.as_str()).ok_or_else(|| Error::InvalidTimezone {
+        span: locate(tree.as_span()),
     })
 }
 
-pub fn datetime(tree: ParseTree) -> Result<DateTime> {
+/// Converts the text of a timezone offset.
+pub fn tz_offset_str(text: &str) -> Option<FixedOffset> {
+    let sign = if text.starts_with('-') { -1 } else { 1 };
+    let hours = text[1..3].parse::<i32>().unwrap();
+    let mins = text.get(4..6).map_or(0, |m| m.parse::<i32>().unwrap());
+
+    FixedOffset::east_opt(sign * (hours * 60 + mins) * 60)
+}
+
+/// Converts a local datetime in the given timezone.
+///
+/// Only named timezones may skip over or repeat a time (e.g by the start or
+/// end of daylight saving time).
+pub fn timezone(
+    tree: ParseTree,
+    naive: &NaiveDateTime,
+) -> Result<(LocalResult<DateTime>, Timezone)> {
+    let text = tree.as_str();
+    match tree.into_inner().next() {
+        Some(name) if name.as_rule() == Rule::tz_name => {
+            let zone = Zone::find(name.as_str()).ok_or_else(|| {
+                Error::UnknownTimezone {
+                    span: locate(name.as_span()),
+                }
+            })?;
+            let zone_name = Timezone::Named(name.as_str().to_string());
+            Ok((zone.localize(naive), zone_name))
+        }
+        Some(offset) => {
+            let offset = tz_offset(offset)?;
+            Ok((offset.from_local_datetime(naive), Timezone::Gmt))
+        }
+        None => {
+            let dtime = Utc.from_utc_datetime(naive).fixed_offset();
+            let zone = if text == "-UTC" {
+                Timezone::Utc
+            } else {
+                Timezone::Gmt
+            };
+            Ok((LocalResult::Single(dtime), zone))
+        }
+    }
+}
+
+pub fn datetime(tree: ParseTree, opts: &ParseOptions) -> Result<Value> {
+    // Locating the span walks the text up to it, so it is left to errors.
+    let span = tree.as_span();
     let mut pairs = tree.into_inner();
     let date = date(pairs.next().unwrap())?;
     let time = time(pairs.next().unwrap())?;
 
     let naive = NaiveDateTime::new(date, time);
 
-    Ok(if pairs.next().is_some() {
-        Utc.fix().from_utc_datetime(&naive)
-    } else {
-        let local = Local.from_local_datetime(&naive).unwrap();
-        local.with_timezone(local.offset())
-    })
+    let dtime = match pairs.next() {
+        Some(tz) => {
+            let (dtime, zone) = timezone(tz, &naive)?;
+            dtime.map(|dtime| Value::DateTime(dtime, zone.clone()))
+        }
+        None => local(&naive, &opts.default_zone),
+    };
+    match dtime {
+        LocalResult::Single(dtime) => Ok(dtime),
+        LocalResult::Ambiguous(..) => {
+            Err(Error::AmbiguousLocalTime { span: locate(span) })
+        }
+        LocalResult::None => {
+            Err(Error::NonexistentLocalTime { span: locate(span) })
+        }
+    }
+}
+
+/// Converts a datetime without a timezone, which is in the given default
+/// zone.
+///
+/// Only local times may be skipped over or repeated (by a change to daylight
+/// saving time, for example).
+pub fn local(naive: &NaiveDateTime, zone: &DefaultZone) -> LocalResult<Value> {
+    let dtime = match zone {
+        DefaultZone::Naive => {
+            return LocalResult::Single(Value::NaiveDateTime(*naive))
+        }
+        DefaultZone::Local => Local
+            .from_local_datetime(naive)
+            .map(|local| local.fixed_offset()),
+        DefaultZone::Utc => {
+            LocalResult::Single(Utc.from_utc_datetime(naive).fixed_offset())
+        }
+        DefaultZone::Fixed(offset) => offset.from_local_datetime(naive),
+    };
+    dtime.map(|dtime| Value::DateTime(dtime, Timezone::Local))
 }
 
 pub fn duration(tree: ParseTree) -> Result<Duration> {
-    let mut dur: Duration = Duration::new(0, 0);
+    let negative = tree.as_str().starts_with('-');
+    let mut dur = Duration::zero();
 
     tree.into_inner().try_for_each(|p| match p.as_rule() {
-        Rule::days => p
-            .as_str()
-            .parse::<u32>()
-            .map(|val| {
-                dur += Duration::from_secs(val as u64 * 24 * 60 * 60);
-            })
-            .map_err(|_| {
-                parse_err(
-                    format!("Could not parse days from '{}'!", p.as_str()),
-                    p.as_span(),
-                )
+        Rule::days => days_str(p.as_str())
+            .map(|days| dur += days)
+            .ok_or_else(|| Error::NumberOutOfRange {
+                span: locate(p.as_span()),
+                suffix: "d".to_string(),
             }),
-        Rule::time => time(p).map(|time| {
-            dur += Duration::new(
-                time.second() as u64
-                    + 60 * (time.minute() as u64 + 60 * time.hour() as u64),
-                time.nanosecond(),
-            );
-        }),
+        Rule::time => time(p).map(|time| dur += time_of_day(time)),
         _ => unreachable!(),
     })?;
 
-    Ok(dur)
+    Ok(if negative { -dur } else { dur })
+}
+
+/// Converts the text of the day count of a duration.
+pub fn days_str(text: &str) -> Option<Duration> {
+    let days = text.parse::<u32>().ok()?;
+    Some(Duration::days(days.into()))
+}
+
+/// Converts the time of a duration.
+pub fn time_of_day(time: NaiveTime) -> Duration {
+    Duration::seconds(time.num_seconds_from_midnight().into())
+        + Duration::nanoseconds(time.nanosecond().into())
 }
 
-pub fn number(tree: ParseTree) -> Result<i128> {
+pub fn number(tree: ParseTree) -> Result<Value> {
     let mut pairs = tree.into_inner();
 
     let num = pairs.next().unwrap();
     let text = num.as_str();
 
-    match pairs.next().map(|p| p.as_str()) {
-        None => text.parse::<i32>().map(|n| n as i128),
-        Some("L") => text.parse::<i64>().map(|n| n as i128),
-        Some("BD") => text.parse::<i128>(),
+    let suffix = pairs.next().map_or("", |p| p.as_str());
+
+    number_str(text, suffix).ok_or_else(|| Error::NumberOutOfRange {
+        span: locate(num.as_span()),
+        suffix: suffix.to_string(),
+    })
+}
+
+/// Converts the text of a number, given its suffix.
+///
+/// With the `bigdecimal` feature, `BD` numbers which do not fit in 128 bits
+/// are big decimals.
+pub fn number_str(text: &str, suffix: &str) -> Option<Value> {
+    let kind = match suffix {
+        "" => NumberKind::Int,
+        "L" => NumberKind::Long,
+        "BD" => NumberKind::BigDecimal,
         _ => unreachable!(),
+    };
+    match text.parse::<i128>().ok().filter(|&num| kind.holds(num)) {
+        Some(num) => Some(Value::Number(num, kind)),
+        #[cfg(feature = "bigdecimal")]
+        None if kind == NumberKind::BigDecimal => {
+            text.parse::<BigDecimal>().ok().map(Value::from)
+        }
+        None => None,
     }
-    .map_err(|_| {
-        parse_err(
-            format!("Error in parsing '{}' as a number (too large?)", text),
-            num.as_span(),
-        )
-    })
 }
 
-pub fn decimal(tree: ParseTree) -> Result<f64> {
+pub fn decimal(tree: ParseTree) -> Result<Value> {
+    let span = tree.as_span();
     let mut pairs = tree.into_inner();
 
     let num = pairs.next().unwrap();
     let text = num.as_str();
 
-    match pairs.next().map(|p| p.as_str()) {
-        Some("f") => text.parse::<f32>().map(|n| n as f64),
-        None => text.parse::<f64>(),
-        _ => unreachable!(),
+    let suffix = pairs.next().map_or("", |p| p.as_str());
+    if suffix == "BD" && cfg!(not(feature = "bigdecimal")) {
+        return Err(Error::UnsupportedBigDecimal {
+            span: locate(span),
+        });
     }
-    .map_err(|_| {
-        parse_err(
-            format!("Error in parsing '{}' as a decimal (too large?)", text),
-            num.as_span(),
-        )
+
+    decimal_str(text, suffix).ok_or_else(|| Error::NumberOutOfRange {
+        span: locate(num.as_span()),
+        suffix: suffix.to_string(),
     })
 }
 
+/// Converts the text of a decimal, given its suffix.
+///
+/// Decimals with the `BD` suffix need the `bigdecimal` feature.
+pub fn decimal_str(text: &str, suffix: &str) -> Option<Value> {
+    match suffix {
+        "f" => text.parse::<f32>().ok().map(Value::from),
+        "" | "d" => text.parse::<f64>().ok().map(Value::from),
+        #[cfg(feature = "bigdecimal")]
+        "BD" => text.parse::<BigDecimal>().ok().map(Value::from),
+        #[cfg(not(feature = "bigdecimal"))]
+        "BD" => None,
+        _ => unreachable!(),
+    }
+}
+
 pub fn boolean(tree: ParseTree) -> Result<bool> {
     Ok(match tree.into_inner().next().unwrap().as_rule() {
         Rule::bool_true => true,
@@ -174,19 +370,22 @@ pub fn base64(tree: ParseTree) -> Result<Vec<u8>> {
             .collect::<Vec<_>>()
             .as_slice(),
     )
-    .map_err(|e| parse_err(format!("Error in parsing Base64: {}", e), span))
+    .map_err(|_| Error::InvalidBase64 {
+        span: locate(span),
+    })
 }
 
-pub fn value(tree: ParseTree) -> Result<Value> {
+pub fn value(tree: ParseTree, opts: &ParseOptions) -> Result<Value> {
     let tree = tree.into_inner().next().unwrap();
     match tree.as_rule() {
         Rule::string => string(tree).map(|v| v.into()),
+        Rule::character => character(tree).map(|v| v.into()),
         Rule::base64 => base64(tree).map(|v| v.into()),
         Rule::date => date(tree).map(|v| v.into()),
-        Rule::datetime => datetime(tree).map(|v| v.into()),
+        Rule::datetime => datetime(tree, opts),
         Rule::duration => duration(tree).map(|v| v.into()),
-        Rule::number => number(tree).map(|v| v.into()),
-        Rule::decimal => decimal(tree).map(|v| v.into()),
+        Rule::number => number(tree),
+        Rule::decimal => decimal(tree),
         Rule::boolean => boolean(tree).map(|v| v.into()),
         Rule::null => Ok(Value::Null),
         _ => unreachable!(),
@@ -197,37 +396,366 @@ pub fn ident(tree: ParseTree) -> Result<String> {
     Ok(tree.as_str().to_string())
 }
 
-pub fn attribute(tree: ParseTree) -> Result<Attribute> {
+pub fn attribute(tree: ParseTree, opts: &ParseOptions) -> Result<Attribute> {
     let mut pairs = tree.into_inner();
     let name = pairs.next().unwrap();
     let val = pairs.next().unwrap();
-    Ok((ident(name)?, value(val)?).into())
+    Ok((ident(name)?, value(val, opts)?).into())
 }
 
 pub fn namespace(tree: ParseTree) -> Result<String> {
     ident(tree.into_inner().next().unwrap())
 }
 
-pub fn tag(tree: ParseTree) -> Result<Tag> {
-    println!("{}", tree);
-    tree.into_inner()
-        .try_fold(Tag::new(String::new()), |mut tag, tree| {
-            match tree.as_rule() {
-                Rule::namespace => tag.namespace = Some(namespace(tree)?),
-                Rule::ident => tag.name = ident(tree)?,
-                Rule::value => tag.values.push(value(tree)?),
-                Rule::attribute => tag.attrs.push(attribute(tree)?),
-                Rule::tags => tag.tags.append(&mut tags(tree)?),
-                _ => unreachable!(),
+/// Receives the items of a tag from `visit_tag`.
+pub trait TagSink<'i> {
+    /// Locates an item of the tag (or the tag itself), for `trace`.
+    fn span(&self, tree: &ParseTree<'i>) -> Span;
+    fn namespace(&mut self, tree: ParseTree<'i>) -> Result<()>;
+    fn name(&mut self, tree: ParseTree<'i>) -> Result<()>;
+    fn value(&mut self, tree: ParseTree<'i>) -> Result<()>;
+    fn attribute(&mut self, tree: ParseTree<'i>) -> Result<()>;
+    /// Receives the child tags of a tag, or the brace opening them if only
+    /// the start of the tag was parsed.
+    fn children(&mut self, tree: ParseTree<'i>) -> Result<()>;
+
+    /// Handles an error in an item, which stops the walk by default.
+    fn error(&mut self, err: Error) -> Result<()> {
+        Err(err)
+    }
+}
+
+/// Walks the items of a tag (`Rule::tag` or `Rule::tag_start`) in order,
+/// handing them to the sink.
+///
+/// The tag and every item but its child tags are reported through `trace`.
+pub fn visit_tag<'i, S: TagSink<'i>>(
+    tree: ParseTree<'i>,
+    sink: &mut S,
+) -> Result<()> {
+    trace(&tree, || sink.span(&tree));
+    tree.into_inner().try_for_each(|tree| {
+        let res = match tree.as_rule() {
+            Rule::tags | Rule::open_brace => sink.children(tree),
+            rule => {
+                trace(&tree, || sink.span(&tree));
+                match rule {
+                    Rule::namespace => sink.namespace(tree),
+                    Rule::ident => sink.name(tree),
+                    Rule::value => sink.value(tree),
+                    Rule::attribute => sink.attribute(tree),
+                    _ => unreachable!(),
+                }
             }
-            Ok(tag)
-        })
+        };
+        res.or_else(|err| sink.error(err))
+    })
+}
+
+/// Collects the items of a tag into a `Tag`, leaving its child tags to the
+/// caller.
+pub struct TagItems<'i, 'o, F> {
+    pub tag: Tag,
+    /// The child tags, or the brace opening them.
+    pub children: Option<ParseTree<'i>>,
+    /// The errors in the items, if they are collected rather than stopping
+    /// the walk.
+    pub errors: Option<Vec<Error>>,
+    /// Locates the span of a tree.
+    locate: F,
+    opts: &'o ParseOptions,
+}
+
+impl<'i, 'o, F: Fn(pest::Span<'i>) -> Span> TagItems<'i, 'o, F> {
+    pub fn new(locate: F, opts: &'o ParseOptions) -> Self {
+        TagItems {
+            tag: Tag::new(String::new()),
+            children: None,
+            errors: None,
+            locate,
+            opts,
+        }
+    }
+}
+
+impl<'i, F: Fn(pest::Span<'i>) -> Span> TagSink<'i> for TagItems<'i, '_, F> {
+    fn span(&self, tree: &ParseTree<'i>) -> Span {
+        (self.locate)(tree.as_span())
+    }
+
+    fn namespace(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.tag.namespace = Some(namespace(tree)?);
+        Ok(())
+    }
+
+    fn name(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.tag.name = ident(tree)?;
+        Ok(())
+    }
+
+    fn value(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.tag.values.push(value(tree, self.opts)?);
+        Ok(())
+    }
+
+    fn attribute(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.tag.attrs.push(attribute(tree, self.opts)?);
+        Ok(())
+    }
+
+    fn children(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.children = Some(tree);
+        Ok(())
+    }
+
+    fn error(&mut self, err: Error) -> Result<()> {
+        match &mut self.errors {
+            Some(errors) => {
+                errors.push(err);
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+}
+
+pub fn tag(tree: ParseTree, opts: &ParseOptions) -> Result<Tag> {
+    let mut items = TagItems::new(locate, opts);
+    visit_tag(tree, &mut items)?;
+    let mut tag = items.tag;
+    if let Some(children) = items.children {
+        tag.tags = tags(children, opts)?;
+    }
+    Ok(tag)
+}
+
+pub fn tags(tree: ParseTree, opts: &ParseOptions) -> Result<Vec<Tag>> {
+    tree.into_inner().map(|tree| tag(tree, opts)).collect()
+}
+
+pub fn tagtree(tree: ParseTree, opts: &ParseOptions) -> Result<Vec<Tag>> {
+    tags(tree.into_inner().next().unwrap(), opts)
+}
+
+/// Reports the parsing of a tag, or of an item in a tag, through `log` (if the
+/// `log` feature is enabled).
+///
+/// Events are logged at the `trace` level, with the span of the item; the
+/// span is only computed if the event is actually logged.
+#[allow(unused_variables)]
+pub fn trace<F>(tree: &ParseTree, span: F)
+where
+    F: FnOnce() -> Span,
+{
+    #[cfg(feature = "log")]
+    if log::log_enabled!(log::Level::Trace) {
+        match tree.as_rule() {
+            // The text of a tag may span many lines, and is reported
+            // piecewise.
+            Rule::tag | Rule::tag_start => {
+                log::trace!("{} at {}", describe(tree.as_rule()), span())
+            }
+            rule => log::trace!(
+                "{} `{}` at {}",
+                describe(rule),
+                tree.as_str(),
+                span()
+            ),
+        }
+    }
+}
+
+// Span-recording variants of the tag routines.
+
+pub fn attribute_spanned(
+    tree: ParseTree,
+    lines: &LineIndex,
+    opts: &ParseOptions,
+) -> Result<(Attribute, AttrSpans)> {
+    let span = lines.span(tree.as_span());
+    let mut pairs = tree.into_inner();
+    let name = pairs.next().unwrap();
+    let val = pairs.next().unwrap();
+    let spans = AttrSpans {
+        span,
+        name: lines.span(name.as_span()),
+        value: lines.span(val.as_span()),
+    };
+    Ok(((ident(name)?, value(val, opts)?).into(), spans))
+}
+
+/// Collects the items of a tag along with their spans.
+struct SpannedItems<'l, 't> {
+    tag: Tag,
+    spans: TagSpans,
+    lines: &'l LineIndex<'t>,
+    opts: &'l ParseOptions,
+}
+
+impl<'i> TagSink<'i> for SpannedItems<'_, '_> {
+    fn span(&self, tree: &ParseTree<'i>) -> Span {
+        self.lines.span(tree.as_span())
+    }
+
+    fn namespace(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        let name = tree.clone().into_inner().next().unwrap();
+        self.spans.namespace = Some(self.lines.span(name.as_span()));
+        self.tag.namespace = Some(namespace(tree)?);
+        Ok(())
+    }
+
+    fn name(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.spans.name = Some(self.span(&tree));
+        self.tag.name = ident(tree)?;
+        Ok(())
+    }
+
+    fn value(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.spans.values.push(self.span(&tree));
+        self.tag.values.push(value(tree, self.opts)?);
+        Ok(())
+    }
+
+    fn attribute(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        let (attr, attr_spans) =
+            attribute_spanned(tree, self.lines, self.opts)?;
+        self.spans.attrs.push(attr_spans);
+        self.tag.attrs.push(attr);
+        Ok(())
+    }
+
+    fn children(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        let (tags, tag_spans) = tags_spanned(tree, self.lines, self.opts)?;
+        self.spans.tags.extend(tag_spans);
+        self.tag.tags.extend(tags);
+        Ok(())
+    }
+}
+
+pub fn tag_spanned(
+    tree: ParseTree,
+    lines: &LineIndex,
+    opts: &ParseOptions,
+) -> Result<(Tag, TagSpans)> {
+    let mut items = SpannedItems {
+        tag: Tag::new(String::new()),
+        spans: TagSpans::new(lines.span(tree.as_span())),
+        lines,
+        opts,
+    };
+    visit_tag(tree, &mut items)?;
+    Ok((items.tag, items.spans))
+}
+
+pub fn tags_spanned(
+    tree: ParseTree,
+    lines: &LineIndex,
+    opts: &ParseOptions,
+) -> Result<(Vec<Tag>, Vec<TagSpans>)> {
+    tree.into_inner()
+        .map(|tree| tag_spanned(tree, lines, opts))
+        .collect::<Result<Vec<_>>>()
+        .map(|tags| tags.into_iter().unzip())
+}
+
+pub fn tagtree_spanned(
+    tree: ParseTree,
+    lines: &LineIndex,
+    opts: &ParseOptions,
+) -> Result<(Vec<Tag>, Vec<TagSpans>)> {
+    tags_spanned(tree.into_inner().next().unwrap(), lines, opts)
+}
+
+// Borrowing variants of the tag routines.
+
+pub fn string_ref(tree: ParseTree<'_>) -> Result<Cow<'_, str>> {
+    let text = tree.as_str();
+    let inner = &text[1..text.len() - 1];
+    if text.starts_with('`') || !inner.contains('\\') {
+        Ok(Cow::Borrowed(inner))
+    } else {
+        string(tree).map(Cow::Owned)
+    }
+}
+
+pub fn value_ref<'i>(
+    tree: ParseTree<'i>,
+    opts: &ParseOptions,
+) -> Result<ValueRef<'i>> {
+    let inner = tree.clone().into_inner().next().unwrap();
+    match inner.as_rule() {
+        Rule::string => string_ref(inner).map(ValueRef::String),
+        _ => value(tree, opts).map(ValueRef::Other),
+    }
+}
+
+pub fn attribute_ref<'i>(
+    tree: ParseTree<'i>,
+    opts: &ParseOptions,
+) -> Result<AttributeRef<'i>> {
+    let mut pairs = tree.into_inner();
+    let name = pairs.next().unwrap();
+    let val = pairs.next().unwrap();
+    Ok(AttributeRef {
+        name: Cow::Borrowed(name.as_str()),
+        value: value_ref(val, opts)?,
+    })
+}
+
+/// Collects the items of a tag, borrowing from the text.
+struct BorrowedItems<'i, 'o>(TagRef<'i>, &'o ParseOptions);
+
+impl<'i> TagSink<'i> for BorrowedItems<'i, '_> {
+    fn span(&self, tree: &ParseTree<'i>) -> Span {
+        locate(tree.as_span())
+    }
+
+    fn namespace(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        let name = tree.into_inner().next().unwrap();
+        self.0.namespace = Some(Cow::Borrowed(name.as_str()));
+        Ok(())
+    }
+
+    fn name(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.0.name = Cow::Borrowed(tree.as_str());
+        Ok(())
+    }
+
+    fn value(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.0.values.push(value_ref(tree, self.1)?);
+        Ok(())
+    }
+
+    fn attribute(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.0.attrs.push(attribute_ref(tree, self.1)?);
+        Ok(())
+    }
+
+    fn children(&mut self, tree: ParseTree<'i>) -> Result<()> {
+        self.0.tags = tags_ref(tree, self.1)?;
+        Ok(())
+    }
+}
+
+pub fn tag_ref<'i>(
+    tree: ParseTree<'i>,
+    opts: &ParseOptions,
+) -> Result<TagRef<'i>> {
+    let mut items = BorrowedItems(TagRef::new("".into()), opts);
+    visit_tag(tree, &mut items)?;
+    Ok(items.0)
 }
 
-pub fn tags(tree: ParseTree) -> Result<Vec<Tag>> {
-    tree.into_inner().map(|tree| tag(tree)).collect()
+pub fn tags_ref<'i>(
+    tree: ParseTree<'i>,
+    opts: &ParseOptions,
+) -> Result<Vec<TagRef<'i>>> {
+    tree.into_inner().map(|tree| tag_ref(tree, opts)).collect()
 }
 
-pub fn tagtree(tree: ParseTree) -> Result<Vec<Tag>> {
-    tags(tree.into_inner().next().unwrap())
+pub fn tagtree_ref<'i>(
+    tree: ParseTree<'i>,
+    opts: &ParseOptions,
+) -> Result<Vec<TagRef<'i>>> {
+    tags_ref(tree.into_inner().next().unwrap(), opts)
 }
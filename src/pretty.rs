@@ -0,0 +1,106 @@
+//! Pretty-printing configuration for the emitter.
+//!
+//! The configuration only affects layout; the emitted tags are always the
+//! same when parsed back.
+
+/// Where the opening brace of a tag's children is placed.
+///
+/// SDLang tags end at the end of a line, so a brace on the next line has to be
+/// preceded by a line continuation (`\`).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BraceStyle {
+    /// On the same line as the tag: `tag {`.
+    SameLine,
+    /// On a continued line below the tag, at the tag's indentation.
+    NextLine,
+}
+
+/// Layout options for emitting tag trees.
+///
+/// The default configuration is the one used by `emit_text`: four spaces of
+/// indentation, one tag per line, and no wrapping.
+///
+/// Like `Tag`, it can be built up through chained method calls:
+/// ```rust
+/// use sdlang::{BraceStyle, PrettyConfig};
+///
+/// let config = PrettyConfig::new()
+///     .indent("\t")
+///     .max_width(100)
+///     .brace_style(BraceStyle::NextLine);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PrettyConfig {
+    /// The string used for one level of indentation.
+    pub indent: String,
+    /// The width after which lines are wrapped, if wrapping is enabled.
+    ///
+    /// Indentation is measured in characters, so tabs count as one column.
+    pub max_width: usize,
+    /// Whether to wrap values and attributes which overflow `max_width` onto
+    /// continued lines.
+    pub wrap: bool,
+    /// Where to put the opening brace of child tags.
+    pub brace_style: BraceStyle,
+    /// Whether to put each attribute on its own continued line.
+    pub attr_per_line: bool,
+    /// The number of blank lines between top-level tags.
+    pub top_level_spacing: usize,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        PrettyConfig {
+            indent: "    ".to_string(),
+            max_width: 80,
+            wrap: false,
+            brace_style: BraceStyle::SameLine,
+            attr_per_line: false,
+            top_level_spacing: 0,
+        }
+    }
+}
+
+impl PrettyConfig {
+    /// Creates the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the indentation string.
+    pub fn indent<S: Into<String>>(mut self, indent: S) -> Self {
+        self.indent = indent.into();
+        self
+    }
+
+    /// Sets the maximum line width and enables wrapping.
+    pub fn max_width(mut self, max_width: usize) -> Self {
+        self.max_width = max_width;
+        self.wrap = true;
+        self
+    }
+
+    /// Enables or disables wrapping of long lines.
+    pub fn wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Sets the brace placement.
+    pub fn brace_style(mut self, brace_style: BraceStyle) -> Self {
+        self.brace_style = brace_style;
+        self
+    }
+
+    /// Enables or disables one-attribute-per-line mode.
+    pub fn attr_per_line(mut self, attr_per_line: bool) -> Self {
+        self.attr_per_line = attr_per_line;
+        self
+    }
+
+    /// Sets the number of blank lines between top-level tags.
+    pub fn top_level_spacing(mut self, lines: usize) -> Self {
+        self.top_level_spacing = lines;
+        self
+    }
+}
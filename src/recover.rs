@@ -0,0 +1,249 @@
+//! Parsing with recovery from errors.
+//!
+//! Instead of parsing the whole text with one grammar rule, the list of tags
+//! is walked here, parsing one tag (without its child tags) at a time. After
+//! an error, the broken tag is skipped up to the next tag separator, so that
+//! the errors in the rest of the text are found as well.
+
+use crate::grammar::{self, Rule};
+use crate::parse::{self, TagItems};
+use crate::span::{LineIndex, Span};
+use crate::{Error, ParseOptions, Tag};
+
+/// The maximum number of nested tag blocks, beyond which tags are skipped
+/// instead of overflowing the stack.
+pub const MAX_DEPTH: usize = 256;
+
+pub struct Recovery<'a> {
+    text: &'a str,
+    lines: LineIndex<'a>,
+    opts: &'a ParseOptions,
+    /// The byte offset of the rest of the text.
+    pos: usize,
+    /// The number of enclosing tag blocks.
+    depth: usize,
+    /// The errors found so far.
+    pub errors: Vec<Error>,
+}
+
+impl<'a> Recovery<'a> {
+    pub fn new(text: &'a str, opts: &'a ParseOptions) -> Self {
+        Recovery {
+            text,
+            lines: LineIndex::new(text),
+            opts,
+            pos: 0,
+            depth: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    /// Parses a list of tags, up to the end of the text or, if `nested`, up
+    /// to the closing brace.
+    pub fn tags(&mut self, nested: bool) -> Vec<Tag> {
+        let mut tags = Vec::new();
+        loop {
+            self.skip_white();
+            match self.rest().chars().next() {
+                None => {
+                    if nested {
+                        self.expected("`}`");
+                    }
+                    return tags;
+                }
+                Some('\n') | Some(';') => self.pos += 1,
+                Some('}') if nested => return tags,
+                Some('}') => {
+                    self.expected("a tag");
+                    self.pos += 1;
+                }
+                Some(_) => tags.extend(self.tag()),
+            }
+        }
+    }
+
+    /// Parses a tag and its child tags, returning `None` if there are errors
+    /// in the tag itself.
+    fn tag(&mut self) -> Option<Tag> {
+        let start = self.pos;
+        let tree = match grammar::parse(Rule::tag_start, self.rest()) {
+            Ok(tree) => tree,
+            Err(err) => {
+                self.error(err, start);
+                self.skip_tag();
+                return None;
+            }
+        };
+        let tree_span = tree.as_span();
+        self.pos += tree.as_str().len();
+
+        let mut items = TagItems::new(|span| self.span(span, start), self.opts);
+        items.errors = Some(Vec::new());
+        // Errors are collected, so the walk is never stopped.
+        let _ = parse::visit_tag(tree, &mut items);
+        let (mut tag, block) = (items.tag, items.children.is_some());
+        let errors = items.errors.unwrap_or_default();
+        let valid = errors.is_empty();
+        for err in errors {
+            self.error(err, start);
+        }
+
+        if block && self.depth == MAX_DEPTH {
+            self.errors.push(Error::NestingTooDeep {
+                span: self.span(tree_span, start),
+            });
+            self.pos = start;
+            self.skip_tag();
+            return None;
+        }
+        if block {
+            self.depth += 1;
+            tag.tags = self.tags(true);
+            self.depth -= 1;
+            if self.rest().starts_with('}') {
+                self.pos += 1;
+                self.skip_white();
+                match self.rest().chars().next() {
+                    None | Some('\n') | Some(';') | Some('}') => {}
+                    Some(_) => {
+                        self.expected("a newline or `;`");
+                        self.skip_tag();
+                    }
+                }
+            }
+        }
+        Some(tag).filter(|_| valid)
+    }
+
+    /// Converts the span of a tree parsed from the text starting at the given
+    /// offset.
+    fn span(&self, span: pest::Span, base: usize) -> Span {
+        Span {
+            start: self.lines.position(base + span.start()),
+            end: self.lines.position(base + span.end()),
+        }
+    }
+
+    /// Records an error found in the text starting at the given offset.
+    fn error(&mut self, mut err: Error, base: usize) {
+        if let Some(span) = err.span_mut() {
+            *span = Span {
+                start: self.lines.position(base + span.start.offset),
+                end: self.lines.position(base + span.end.offset),
+            };
+        }
+        self.errors.push(err);
+    }
+
+    /// Records a syntax error at the current offset.
+    fn expected(&mut self, expected: &'static str) {
+        let pos = self.lines.position(self.pos);
+        self.errors.push(Error::Syntax {
+            span: Span {
+                start: pos,
+                end: pos,
+            },
+            expected: vec![expected],
+        });
+    }
+
+    /// Skips whitespace and comments, up to the end of the line.
+    fn skip_white(&mut self) {
+        loop {
+            let rest = self.rest();
+            if rest.starts_with([' ', '\t', '\r']) {
+                self.pos += 1;
+            } else if rest.starts_with("\\\n") {
+                self.pos += 2;
+            } else if !self.skip_comment() {
+                return;
+            }
+        }
+    }
+
+    /// Skips a comment, if there is one.
+    fn skip_comment(&mut self) -> bool {
+        let rest = self.rest();
+        let len = if let Some(body) = rest.strip_prefix("/*") {
+            // Unterminated comments are errors.
+            match body.find("*/") {
+                Some(end) => end + 4,
+                None => return false,
+            }
+        } else if rest.starts_with("//")
+            || rest.starts_with("--")
+            || rest.starts_with('#')
+        {
+            rest.find('\n').unwrap_or(rest.len())
+        } else {
+            return false;
+        };
+        self.pos += len;
+        true
+    }
+
+    /// Skips the rest of a broken tag, including its child tags, up to the
+    /// next tag separator or the closing brace of the enclosing tag.
+    fn skip_tag(&mut self) {
+        let mut depth = 0;
+        while let Some(ch) = self.rest().chars().next() {
+            if self.skip_comment() {
+                continue;
+            }
+            let rest = &self.rest()[ch.len_utf8()..];
+            let len = match ch {
+                '\n' | ';' | '}' if depth == 0 => return,
+                '{' => {
+                    depth += 1;
+                    0
+                }
+                '}' => {
+                    depth -= 1;
+                    0
+                }
+                // Strings may contain anything that ends a tag.
+                '"' => string_len(rest).unwrap_or(rest.len()),
+                '`' => rest.find('`').map_or(rest.len(), |end| end + 1),
+                '\'' => char_len(rest).unwrap_or(0),
+                _ => 0,
+            };
+            self.pos += ch.len_utf8() + len;
+        }
+    }
+}
+
+/// Returns the length of the rest of a string, up to and including the
+/// closing quote (or `None`, if it is unterminated).
+pub fn string_len(rest: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, ch) in rest.char_indices() {
+        match ch {
+            '"' if !escaped => return Some(i + 1),
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Returns the length of the rest of a character, up to and including the
+/// closing quote (or `None`, if it is not a valid one).
+pub fn char_len(rest: &str) -> Option<usize> {
+    let is_unicode = rest.starts_with("\\u")
+        && rest
+            .as_bytes()
+            .get(2..6)
+            .is_some_and(|digits| digits.iter().all(u8::is_ascii_hexdigit));
+    let mut chars = rest.chars();
+    let len = match chars.next()? {
+        _ if is_unicode => 6,
+        '\\' => 1 + chars.next()?.len_utf8(),
+        '\'' => return None,
+        ch => ch.len_utf8(),
+    };
+    Some(len + 1).filter(|_| rest[len..].starts_with('\''))
+}
@@ -0,0 +1,587 @@
+//! Serde serialization into SDLang documents.
+//!
+//! Data is first serialized into an intermediate item tree, which is then laid
+//! out as tags. Look at `de` for the mapping between Rust data and tags.
+
+use serde::ser::{self, Serialize};
+
+use chrono::SecondsFormat;
+
+use crate::{emit, Date, DateTime, Duration, NaiveDateTime, Tag, Value};
+
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The serialization error type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A custom error from a `Serialize` implementation.
+    Custom(String),
+    /// The data has no SDLang representation (e.g a map with non-string keys,
+    /// or an integer beyond `i128`).
+    Unsupported(String),
+    /// The resulting tag tree could not be emitted (e.g a field name which is
+    /// not a valid identifier, or a non-finite decimal).
+    Emit,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Custom(msg) => f.write_str(msg),
+            Error::Unsupported(msg) => write!(f, "Unsupported data: {}", msg),
+            Error::Emit => f.write_str("Tag tree cannot be written as SDLang"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// The serialization result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Serializes the given data into a root tag.
+///
+/// The data must serialize as a struct or a map, whose entries become the
+/// children of the root tag.
+pub fn to_tag<T: Serialize + ?Sized>(data: &T) -> Result<Tag> {
+    match data.serialize(Serializer)? {
+        Item::Map(entries) => {
+            let mut root = Tag::new(String::new());
+            entries
+                .into_iter()
+                .try_for_each(|(k, v)| push_field(&mut root.tags, k, v))?;
+            Ok(root)
+        }
+        _ => Err(Error::Unsupported(
+            "the document root must be a struct or map".to_string(),
+        )),
+    }
+}
+
+/// Serializes the given data into SDLang text.
+///
+/// Look at `to_tag` for more information.
+pub fn to_string<T: Serialize + ?Sized>(data: &T) -> Result<String> {
+    crate::emit_text(&to_tag(data)?).map_err(|_| Error::Emit)
+}
+
+/// Intermediate form of serialized data.
+#[derive(Debug)]
+enum Item {
+    /// An absent optional value.
+    None,
+    /// A single value.
+    Value(Value),
+    /// A sequence, tuple, or tuple struct.
+    Seq(Vec<Item>),
+    /// A struct or a map, with its keys.
+    Map(Vec<(String, Item)>),
+    /// A non-unit enum variant, with its name and contents.
+    Variant(&'static str, Box<Item>),
+    /// A `u8`. Sequences of them are Base64 data, and lone ones are numbers.
+    Byte(u8),
+}
+
+impl Item {
+    /// Whether the item can be stored in the value list of a tag.
+    fn is_value(&self) -> bool {
+        matches!(self, Item::None | Item::Value(_))
+    }
+
+    /// Turns a lone byte into a number.
+    fn number(self) -> Item {
+        match self {
+            Item::Byte(b) => Item::Value(Value::from(i32::from(b))),
+            item => item,
+        }
+    }
+}
+
+/// Recognizes the strings which `chrono` dates and datetimes serialize as.
+///
+/// Only strings in exactly the format produced by `chrono` (and by `de`, when
+/// handing dates and datetimes to visitors) are recognized, so that strings
+/// which merely look like dates still deserialize to the same text.
+fn chrono_value(text: &str) -> Option<Value> {
+    let val = if let Some(date) =
+        text.parse::<Date>().ok().filter(|d| d.to_string() == text)
+    {
+        Value::Date(date)
+    } else if let Some(dtime) = text
+        .parse::<NaiveDateTime>()
+        .ok()
+        .filter(|dt| format!("{:?}", dt) == text)
+    {
+        Value::NaiveDateTime(dtime)
+    } else {
+        let dtime = DateTime::parse_from_rfc3339(text).ok().filter(|dt| {
+            dt.to_rfc3339_opts(SecondsFormat::AutoSi, true) == text
+        })?;
+        Value::from(dtime)
+    };
+    // SDLang only holds four-digit years.
+    emit::value(&mut String::new(), &val).ok().map(|()| val)
+}
+
+/// Adds the tags representing the given field to a list of tags.
+fn push_field(tags: &mut Vec<Tag>, name: String, item: Item) -> Result<()> {
+    match item {
+        // Missing fields are deserialized as `None`.
+        Item::None => Ok(()),
+        Item::Seq(items)
+            if items
+                .iter()
+                .any(|i| matches!(i, Item::Map(_) | Item::Variant(..))) =>
+        {
+            items
+                .into_iter()
+                .try_for_each(|i| push_field(tags, name.clone(), i))
+        }
+        Item::Variant(variant, item) => {
+            let mut tag = Tag::new(variant.to_string()).namespace(name);
+            fill(&mut tag, *item)?;
+            tags.push(tag);
+            Ok(())
+        }
+        item => {
+            let mut tag = Tag::new(name);
+            fill(&mut tag, item)?;
+            tags.push(tag);
+            Ok(())
+        }
+    }
+}
+
+/// Stores the given item as the contents of a tag.
+fn fill(tag: &mut Tag, item: Item) -> Result<()> {
+    match item {
+        Item::None => tag.values.push(Value::Null),
+        Item::Value(val) => tag.values.push(val),
+        Item::Seq(items) => {
+            if items.iter().all(Item::is_value) {
+                tag.values.extend(items.into_iter().map(|i| match i {
+                    Item::Value(val) => val,
+                    _ => Value::Null,
+                }));
+            } else if items.iter().all(|i| matches!(i, Item::Seq(_))) {
+                // Nested sequences are stored as anonymous child tags, like
+                // the rows of a matrix.
+                tag.tags = items
+                    .into_iter()
+                    .map(|i| {
+                        let mut row = Tag::new(String::new());
+                        fill(&mut row, i)?;
+                        // Anonymous tags must have values.
+                        if row.values.is_empty() {
+                            return Err(Error::Unsupported(format!(
+                                "empty or nested row in '{}'",
+                                tag.name
+                            )));
+                        }
+                        Ok(row)
+                    })
+                    .collect::<Result<_>>()?;
+            } else {
+                return Err(Error::Unsupported(format!(
+                    "sequence with mixed contents in '{}'",
+                    tag.name
+                )));
+            }
+        }
+        Item::Map(entries) => entries
+            .into_iter()
+            .try_for_each(|(k, v)| push_field(&mut tag.tags, k, v))?,
+        Item::Variant(variant, _) => {
+            return Err(Error::Unsupported(format!(
+                "enum variant '{}' outside of a field",
+                variant
+            )))
+        }
+        Item::Byte(b) => tag.values.push(Value::from(i32::from(b))),
+    }
+    Ok(())
+}
+
+/// Serializes data into the intermediate item tree.
+struct Serializer;
+
+impl Serializer {
+    /// Creates a number of the smallest kind which holds the integer.
+    fn number<N: Into<i128>>(self, v: N) -> Result<Item> {
+        let num: i128 = v.into();
+        Ok(Item::Value(Value::from(num)))
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Item;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = VariantSerializer<MapSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Item> {
+        Ok(Item::Value(Value::Boolean(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Item> {
+        self.number(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Item> {
+        self.number(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Item> {
+        self.number(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Item> {
+        self.number(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Item> {
+        self.number(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Item> {
+        Ok(Item::Byte(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Item> {
+        self.number(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Item> {
+        self.number(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Item> {
+        self.number(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Item> {
+        if v > i128::MAX as u128 {
+            return Err(Error::Unsupported(format!("{} is too large", v)));
+        }
+        self.number(v as i128)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Item> {
+        Ok(Item::Value(Value::from(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Item> {
+        Ok(Item::Value(Value::from(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Item> {
+        Ok(Item::Value(Value::Char(v)))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Item> {
+        Ok(Item::Value(
+            chrono_value(v).unwrap_or_else(|| Value::String(v.to_string())),
+        ))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Item> {
+        Ok(Item::Value(Value::Base64(v.to_vec())))
+    }
+
+    fn serialize_none(self) -> Result<Item> {
+        Ok(Item::None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, v: &T) -> Result<Item> {
+        v.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Item> {
+        Ok(Item::Value(Value::Null))
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Item> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Item> {
+        Ok(Item::Value(Value::String(variant.to_string())))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        v: &T,
+    ) -> Result<Item> {
+        v.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        v: &T,
+    ) -> Result<Item> {
+        Ok(Item::Variant(
+            variant,
+            Box::new(v.serialize(self)?.number()),
+        ))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer> {
+        Ok(SeqSerializer(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<SeqSerializer>> {
+        Ok(VariantSerializer(variant, self.serialize_seq(Some(len))?))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer> {
+        Ok(MapSerializer {
+            name: None,
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer> {
+        let mut map = self.serialize_map(Some(len))?;
+        map.name = Some(name);
+        Ok(map)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<MapSerializer>> {
+        Ok(VariantSerializer(variant, self.serialize_map(Some(len))?))
+    }
+}
+
+/// Collects the elements of a sequence.
+struct SeqSerializer(Vec<Item>);
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        v: &T,
+    ) -> Result<()> {
+        self.0.push(v.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Item> {
+        if !self.0.is_empty()
+            && self.0.iter().all(|i| matches!(i, Item::Byte(_)))
+        {
+            let bytes = self.0.into_iter().map(|i| match i {
+                Item::Byte(b) => b,
+                _ => unreachable!(),
+            });
+            return Ok(Item::Value(Value::Base64(bytes.collect())));
+        }
+        Ok(Item::Seq(self.0.into_iter().map(Item::number).collect()))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        v: &T,
+    ) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, v)
+    }
+
+    fn end(self) -> Result<Item> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, v)
+    }
+
+    fn end(self) -> Result<Item> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Collects the entries of a map or struct.
+struct MapSerializer {
+    /// The name of the struct, if it is one.
+    name: Option<&'static str>,
+    entries: Vec<(String, Item)>,
+    /// A key awaiting its value.
+    key: Option<String>,
+}
+
+impl MapSerializer {
+    /// Recognizes `std::time::Duration`, which serializes as a struct, and
+    /// the signed durations of `Value` which serialize the same way.
+    fn duration(&self) -> Result<Option<Duration>> {
+        let (secs, nanos) = match (self.name, self.entries.as_slice()) {
+            (
+                Some("Duration"),
+                [(secs_key, Item::Value(Value::Number(secs, _))), (nanos_key, Item::Value(Value::Number(nanos, _)))],
+            ) if secs_key == "secs" && nanos_key == "nanos" => (*secs, *nanos),
+            _ => return Ok(None),
+        };
+        let dur = i64::try_from(secs)
+            .ok()
+            .zip(i64::try_from(nanos).ok())
+            .filter(|(_, nanos)| nanos.abs() < 1_000_000_000)
+            .and_then(|(secs, nanos)| {
+                Duration::try_seconds(secs)?
+                    .checked_add(&Duration::nanoseconds(nanos))
+            });
+        match dur {
+            Some(dur) => Ok(Some(dur)),
+            None => Err(Error::Unsupported(format!(
+                "duration of {} seconds and {} nanoseconds is out of range",
+                secs, nanos
+            ))),
+        }
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(Serializer)? {
+            Item::Value(Value::String(key)) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            key => Err(Error::Unsupported(format!(
+                "map key {:?} is not a string",
+                key
+            ))),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<()> {
+        let key = self.key.take().expect("value serialized before its key");
+        self.entries.push((key, v.serialize(Serializer)?.number()));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Item> {
+        Ok(match self.duration()? {
+            Some(dur) => Item::Value(Value::Duration(dur)),
+            None => Item::Map(self.entries),
+        })
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        v: &T,
+    ) -> Result<()> {
+        self.entries
+            .push((key.to_string(), v.serialize(Serializer)?.number()));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Item> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// Collects the contents of an enum variant.
+struct VariantSerializer<S>(&'static str, S);
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(&mut self.1, v)
+    }
+
+    fn end(self) -> Result<Item> {
+        Ok(Item::Variant(
+            self.0,
+            Box::new(ser::SerializeSeq::end(self.1)?),
+        ))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<MapSerializer> {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        v: &T,
+    ) -> Result<()> {
+        ser::SerializeStruct::serialize_field(&mut self.1, key, v)
+    }
+
+    fn end(self) -> Result<Item> {
+        Ok(Item::Variant(
+            self.0,
+            Box::new(ser::SerializeMap::end(self.1)?),
+        ))
+    }
+}
@@ -0,0 +1,130 @@
+//! Source positions of parsed items.
+//!
+//! Spans are recorded in a tree parallel to the `Tag` tree, so that the tags
+//! themselves stay free of source information. The `n`th entry of
+//! `TagSpans::values` is the span of the `n`th value of the matching tag, and
+//! so on for attributes and child tags.
+
+use std::fmt;
+
+/// A position in the source text.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Position {
+    /// The byte offset from the start of the text.
+    pub offset: usize,
+    /// The line number, starting at 1.
+    pub line: usize,
+    /// The column number in characters, starting at 1.
+    pub col: usize,
+}
+
+impl fmt::Display for Position {
+    /// Formats as `<line>:<col>`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// A range of the source text.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    /// The position of the first character.
+    pub start: Position,
+    /// The position just after the last character.
+    pub end: Position,
+}
+
+impl Span {
+    /// Returns the byte range of the span, for slicing the source text.
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+impl fmt::Display for Span {
+    /// Formats as `<line>:<col>-<line>:<col>`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// The spans of an attribute.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AttrSpans {
+    /// The whole attribute.
+    pub span: Span,
+    /// The name of the attribute.
+    pub name: Span,
+    /// The value of the attribute.
+    pub value: Span,
+}
+
+/// The spans of a tag and everything in it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TagSpans {
+    /// The whole tag, including its child tags.
+    pub span: Span,
+    /// The namespace, without the colon (if any).
+    pub namespace: Option<Span>,
+    /// The name (`None` for anonymous and root tags).
+    pub name: Option<Span>,
+    /// The spans of the values.
+    pub values: Vec<Span>,
+    /// The spans of the attributes.
+    pub attrs: Vec<AttrSpans>,
+    /// The spans of the child tags.
+    pub tags: Vec<TagSpans>,
+}
+
+impl TagSpans {
+    /// Creates empty spans for a tag covering the given span.
+    pub fn new(span: Span) -> Self {
+        TagSpans {
+            span,
+            namespace: None,
+            name: None,
+            values: Vec::new(),
+            attrs: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// Converts byte offsets into line and column numbers.
+pub struct LineIndex<'a> {
+    text: &'a str,
+    /// The byte offset of the start of every line.
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Indexes the lines of the given text.
+    pub fn new(text: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { text, starts }
+    }
+
+    /// Returns the position of the given byte offset.
+    pub fn position(&self, offset: usize) -> Position {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let col = self.text[self.starts[line]..offset].chars().count() + 1;
+        Position {
+            offset,
+            line: line + 1,
+            col,
+        }
+    }
+
+    /// Converts a Pest span into a span.
+    pub fn span(&self, span: pest::Span) -> Span {
+        Span {
+            start: self.position(span.start()),
+            end: self.position(span.end()),
+        }
+    }
+}
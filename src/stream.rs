@@ -0,0 +1,465 @@
+//! Streaming parsing, as a series of events.
+//!
+//! The text is read in chunks of at most `CHUNK` bytes (ending early at line
+//! breaks), and only the head of the current tag (up to its separator or the
+//! brace opening its child tags) is parsed at a time. Text is dropped as soon
+//! as it has been parsed, so memory use depends on the length of the largest
+//! tag head (including a trailing comment) rather than on the size of the text
+//! or of its lines.
+
+use crate::grammar::{self, Rule};
+use crate::parse::{self, TagItems};
+use crate::recover::{char_len, string_len};
+use crate::span::{LineIndex, Position, Span};
+use crate::{Attribute, Error, ParseOptions, Result, Value};
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read};
+use std::str;
+
+/// The maximum number of bytes read at once.
+const CHUNK: usize = 8192;
+
+/// An item of a document, as parsed by `Events`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Event {
+    /// The start of a tag. It is followed by the values, attributes and child
+    /// tags of the tag, and then by the matching `EndTag`.
+    StartTag {
+        /// The namespace of the tag (if any).
+        namespace: Option<String>,
+        /// The name of the tag (`""` for anonymous tags).
+        name: String,
+    },
+    /// A value of the current tag.
+    Value(Value),
+    /// An attribute of the current tag.
+    Attribute(Attribute),
+    /// The end of the current tag.
+    EndTag,
+}
+
+/// A streaming parser, reading a document from a Reader as it goes.
+///
+/// It is an iterator of events, which describe the tags of the document in
+/// order: every tag is reported as a `StartTag`, its values, its attributes,
+/// its child tags and an `EndTag`. The whole document is never held in
+/// memory, so this is suited to very large documents.
+/// ```rust
+/// use sdlang::{Event, Value};
+///
+/// let text = "server \"main\" port=80 {\n    alias \"www\"\n}\n";
+/// let events = sdlang::parse_events(text.as_bytes())
+///     .collect::<sdlang::Result<Vec<_>>>()
+///     .unwrap();
+/// assert_eq!(
+///     events[..2],
+///     [
+///         Event::StartTag {
+///             namespace: None,
+///             name: "server".to_string()
+///         },
+///         Event::Value(Value::String("main".to_string())),
+///     ]
+/// );
+/// assert_eq!(events.len(), 7);
+/// ```
+///
+/// Parsing stops at the first error, which is the last item of the iterator.
+/// Errors are located in the whole text, as with `parse_text`; errors in
+/// reading are reported as `Error::Io`.
+pub struct Events<R> {
+    reader: io::BufReader<R>,
+    /// The maximum number of bytes read at once.
+    chunk: usize,
+    options: ParseOptions,
+    /// The text which has been read, but not fully parsed.
+    buf: String,
+    /// The bytes of a character split between chunks.
+    partial: Vec<u8>,
+    /// The byte offset of the rest of the buffer.
+    pos: usize,
+    /// The byte offset of the start of the buffer in the whole text.
+    offset: usize,
+    /// The number of lines before the start of the buffer.
+    lines: usize,
+    /// The number of characters of the first line of the buffer which have
+    /// been dropped.
+    col: usize,
+    /// Whether the reader has been exhausted.
+    eof: bool,
+    /// The number of tags whose child tags are being parsed.
+    depth: usize,
+    /// Whether the closing brace of a tag was just parsed, after which only a
+    /// separator may follow.
+    closed: bool,
+    /// The events which have been parsed, but not yet returned.
+    queue: VecDeque<Event>,
+    /// Whether the end of the text or an error has been reached.
+    done: bool,
+}
+
+impl<R: io::Read> Events<R> {
+    /// Creates a streaming parser for the given Reader.
+    ///
+    /// The reader is internally buffered using `std::io::BufReader`.
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, &ParseOptions::default())
+    }
+
+    /// Creates a streaming parser for the given Reader, with the given
+    /// options.
+    pub fn with_options(reader: R, options: &ParseOptions) -> Self {
+        Events {
+            reader: io::BufReader::new(reader),
+            chunk: CHUNK,
+            options: options.clone(),
+            buf: String::new(),
+            partial: Vec::new(),
+            pos: 0,
+            offset: 0,
+            lines: 0,
+            col: 0,
+            eof: false,
+            depth: 0,
+            closed: false,
+            queue: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Creates a streaming parser reading at most `chunk` bytes at once.
+    #[cfg(test)]
+    pub(crate) fn with_chunk(reader: R, chunk: usize) -> Self {
+        Events {
+            chunk,
+            ..Self::new(reader)
+        }
+    }
+
+    fn rest(&self) -> &str {
+        &self.buf[self.pos..]
+    }
+
+    /// Parses up to the next event, leaving the queue empty only at the end
+    /// of the text.
+    fn advance(&mut self) -> Result<()> {
+        self.discard();
+        loop {
+            self.skip_white()?;
+            let next = self.rest().chars().next();
+            if self.closed {
+                match next {
+                    None | Some('\n') | Some(';') | Some('}') => {
+                        self.closed = false
+                    }
+                    Some(_) => return Err(self.expected("a newline or `;`")),
+                }
+            }
+            match next {
+                None => {
+                    self.discard();
+                    if self.read(None)? {
+                        continue;
+                    }
+                    if self.depth > 0 {
+                        return Err(self.expected("`}`"));
+                    }
+                    return Ok(());
+                }
+                Some('\n') | Some(';') => self.pos += 1,
+                Some('}') if self.depth > 0 => {
+                    self.pos += 1;
+                    self.depth -= 1;
+                    self.closed = true;
+                    self.queue.push_back(Event::EndTag);
+                    return Ok(());
+                }
+                Some(_) => return self.tag(),
+            }
+        }
+    }
+
+    /// Parses the head of a tag, queueing its events.
+    fn tag(&mut self) -> Result<()> {
+        self.fill()?;
+        let start = self.pos;
+        let tree = grammar::parse(Rule::tag_start, self.rest())
+            .map_err(|err| self.error(err, start))?;
+        let len = tree.as_str().len();
+
+        let mut items =
+            TagItems::new(|span| self.span(span, start), &self.options);
+        parse::visit_tag(tree, &mut items)
+            .map_err(|err| self.error(err, start))?;
+        let (tag, block) = (items.tag, items.children.is_some());
+
+        self.pos += len;
+        self.queue.push_back(Event::StartTag {
+            namespace: tag.namespace,
+            name: tag.name,
+        });
+        self.queue.extend(tag.values.into_iter().map(Event::Value));
+        self.queue
+            .extend(tag.attrs.into_iter().map(Event::Attribute));
+        if block {
+            self.depth += 1;
+        } else {
+            self.queue.push_back(Event::EndTag);
+        }
+        Ok(())
+    }
+
+    /// Reads more chunks, up to the one which completes `until` (or just one
+    /// chunk).
+    ///
+    /// Returns whether anything was read.
+    fn read(&mut self, until: Option<&str>) -> io::Result<bool> {
+        let mut read = false;
+        while !self.eof {
+            let start = self.buf.len();
+            self.read_chunk()?;
+            if self.buf.len() == start {
+                continue;
+            }
+            read = true;
+            // `until` may straddle the previous chunk.
+            let from = until.map_or(start, |until| {
+                let mut from = start.saturating_sub(until.len() - 1);
+                while !self.buf.is_char_boundary(from) {
+                    from -= 1;
+                }
+                from
+            });
+            if until.is_none_or(|until| self.buf[from..].contains(until)) {
+                break;
+            }
+        }
+        Ok(read)
+    }
+
+    /// Reads a chunk, up to and including the next line break, appending its
+    /// complete characters to the buffer.
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let limit = self.chunk as u64;
+        if (&mut self.reader)
+            .take(limit)
+            .read_until(b'\n', &mut self.partial)?
+            == 0
+        {
+            self.eof = true;
+            if !self.partial.is_empty() {
+                return Err(invalid_utf8());
+            }
+        }
+        let valid = match str::from_utf8(&self.partial) {
+            Ok(text) => text.len(),
+            Err(err) if err.error_len().is_none() && !self.eof => {
+                err.valid_up_to()
+            }
+            Err(_) => return Err(invalid_utf8()),
+        };
+        let text = str::from_utf8(&self.partial[..valid]).unwrap();
+        self.buf.push_str(text);
+        self.partial.drain(..valid);
+        Ok(())
+    }
+
+    /// Drops the text which has been fully parsed from the buffer.
+    fn discard(&mut self) {
+        let done = &self.buf[..self.pos];
+        match done.rfind('\n') {
+            Some(end) => {
+                self.lines += done.matches('\n').count();
+                self.col = done[end + 1..].chars().count();
+            }
+            None => self.col += done.chars().count(),
+        }
+        self.offset += self.pos;
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+    }
+
+    /// Reads chunks until the rest of the buffer holds the whole head of the
+    /// next tag, up to its separator or opening brace (or the end of the
+    /// text).
+    ///
+    /// Strings, Base64 data and comments may contain separators or span
+    /// multiple lines, so they are skipped over.
+    fn fill(&mut self) -> io::Result<()> {
+        let mut i = self.pos;
+        loop {
+            // Comments and line continuations are told apart by their first
+            // two characters.
+            if self.buf.len() - i < 2 && self.read(None)? {
+                continue;
+            }
+            let rest = &self.buf[i..];
+            let ch = match rest.chars().next() {
+                Some(ch) => ch,
+                None => return Ok(()),
+            };
+            let len = match ch {
+                '\n' | ';' | '{' | '}' => return Ok(()),
+                '"' => {
+                    string_len(&rest[1..]).map(|len| len + 1).ok_or(Some("\""))
+                }
+                '`' => rest[1..].find('`').map(|end| end + 2).ok_or(Some("`")),
+                // Characters may be separators, such as `';'`.
+                '\'' => {
+                    char_len(&rest[1..]).map(|len| len + 1).ok_or(Some("'"))
+                }
+                '[' => rest.find(']').map(|end| end + 1).ok_or(Some("]")),
+                // A line continuation, or a backslash which is an error.
+                '\\' => rest[1..]
+                    .chars()
+                    .next()
+                    .map(|ch| ch.len_utf8() + 1)
+                    .ok_or(Some("\n")),
+                '/' if rest.starts_with("/*") => {
+                    rest[2..].find("*/").map(|end| end + 4).ok_or(Some("*/"))
+                }
+                _ if rest.starts_with("//")
+                    || rest.starts_with("--")
+                    || rest.starts_with('#') =>
+                {
+                    rest.find('\n').ok_or(Some("\n"))
+                }
+                // Identifiers and numbers may contain `--`, which would
+                // otherwise start a comment, so they are read whole.
+                ch if is_ident_char(ch) => {
+                    rest.find(|ch| !is_ident_char(ch)).ok_or(None)
+                }
+                ch => Ok(ch.len_utf8()),
+            };
+            match len {
+                Ok(len) => i += len,
+                Err(until) => {
+                    if !self.read(until)? {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Skips whitespace and comments, up to the end of the line.
+    fn skip_white(&mut self) -> io::Result<()> {
+        loop {
+            if self.rest().len() < 2 && !self.eof {
+                self.discard();
+                if self.read(None)? {
+                    continue;
+                }
+            }
+            let rest = self.rest();
+            if rest.starts_with([' ', '\t', '\r']) {
+                self.pos += 1;
+            } else if rest.starts_with("\\\n") {
+                self.pos += 2;
+            } else if let Some(body) = rest.strip_prefix("/*") {
+                match body.find("*/") {
+                    Some(end) => self.pos += end + 4,
+                    // Unterminated comments are left for the parser to report.
+                    None if !self.read(Some("*/"))? => return Ok(()),
+                    None => {}
+                }
+            } else if rest.starts_with("//")
+                || rest.starts_with("--")
+                || rest.starts_with('#')
+            {
+                self.skip_line()?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Skips up to the end of the line, dropping the skipped text as it goes.
+    fn skip_line(&mut self) -> io::Result<()> {
+        loop {
+            if let Some(end) = self.rest().find('\n') {
+                self.pos += end;
+                return Ok(());
+            }
+            self.pos = self.buf.len();
+            self.discard();
+            if !self.read(None)? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns the position of the given byte offset in the buffer.
+    fn position(&self, offset: usize) -> Position {
+        let pos = LineIndex::new(&self.buf).position(offset);
+        Position {
+            offset: self.offset + pos.offset,
+            line: self.lines + pos.line,
+            col: if pos.line == 1 {
+                self.col + pos.col
+            } else {
+                pos.col
+            },
+        }
+    }
+
+    /// Converts the span of a tree parsed from the buffer starting at the
+    /// given offset.
+    fn span(&self, span: pest::Span, base: usize) -> Span {
+        Span {
+            start: self.position(base + span.start()),
+            end: self.position(base + span.end()),
+        }
+    }
+
+    /// Locates an error found in the buffer starting at the given offset.
+    fn error(&self, mut err: Error, base: usize) -> Error {
+        if let Some(span) = err.span_mut() {
+            *span = Span {
+                start: self.position(base + span.start.offset),
+                end: self.position(base + span.end.offset),
+            };
+        }
+        err
+    }
+
+    /// Creates a syntax error at the current offset.
+    fn expected(&self, expected: &'static str) -> Error {
+        let pos = self.position(self.pos);
+        Error::Syntax {
+            span: Span {
+                start: pos,
+                end: pos,
+            },
+            expected: vec![expected],
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for Events<R> {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Result<Event>> {
+        if self.queue.is_empty() && !self.done {
+            if let Err(err) = self.advance() {
+                self.done = true;
+                return Some(Err(err));
+            }
+            self.done = self.queue.is_empty();
+        }
+        self.queue.pop_front().map(Ok)
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+/// Whether the character may be part of an identifier.
+fn is_ident_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || matches!(ch, '.' | '$' | '-' | '_')
+}
@@ -0,0 +1,47 @@
+use super::gen;
+use super::utils::tree_config;
+use crate::{parse_text, parse_text_borrowed, ValueRef};
+
+use proptest::prelude::*;
+
+use std::borrow::Cow;
+
+#[test]
+fn borrowing() {
+    let text = "ns:a \"x\" `raw\\n` \"esc\\n\" 1 key=\"v\"";
+    let root = parse_text_borrowed(text).unwrap();
+    let tag = &root.tags[0];
+    assert!(matches!(tag.namespace, Some(Cow::Borrowed("ns"))));
+    assert!(matches!(tag.name, Cow::Borrowed("a")));
+    assert!(matches!(
+        tag.values[0],
+        ValueRef::String(Cow::Borrowed("x"))
+    ));
+    assert!(matches!(
+        tag.values[1],
+        ValueRef::String(Cow::Borrowed("raw\\n"))
+    ));
+    match &tag.values[2] {
+        ValueRef::String(Cow::Owned(text)) => assert_eq!(text, "esc\n"),
+        value => panic!("unexpected value: {:?}", value),
+    }
+    assert_eq!(tag.values[3], ValueRef::Other(1.into()));
+    assert!(matches!(tag.attrs[0].name, Cow::Borrowed("key")));
+    assert!(matches!(
+        tag.attrs[0].value,
+        ValueRef::String(Cow::Borrowed("v"))
+    ));
+    assert_eq!(root.into_owned(), parse_text(text).unwrap());
+
+    assert!(parse_text_borrowed("a 2019/02/30").is_err());
+}
+
+proptest! {
+    #![proptest_config(tree_config())]
+
+    #[test]
+    fn tagtree(test in gen::tagtree()) {
+        let root = parse_text_borrowed(&test.text).unwrap();
+        assert_eq!(root.into_owned().tags, test.result);
+    }
+}
@@ -0,0 +1,52 @@
+use super::gen;
+use super::utils::tree_config;
+use crate::cst::{Element, NodeKind, TokenKind};
+use crate::{Document, Tag, Value};
+
+use proptest::prelude::*;
+
+#[test]
+fn trivia() {
+    let text =
+        "# Server\nserver \"main\" { /* inline */\n    port 80 -- HTTP\n}\n";
+    let doc = text.parse::<Document>().unwrap();
+    assert_eq!(doc.to_string(), text);
+
+    let server = doc.root.tags().next().unwrap();
+    assert_eq!(server.name(), "server");
+    let port = server.tags().next().unwrap();
+    assert_eq!(port.values().next().unwrap().text, "80");
+    let comments = server
+        .children
+        .iter()
+        .chain(&port.children)
+        .filter_map(|e| match e {
+            Element::Token(t) if t.kind == TokenKind::Comment => {
+                Some(t.text.as_str())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(comments, ["/* inline */", "-- HTTP"]);
+
+    assert_eq!(
+        doc.to_tag().unwrap().tags,
+        vec![Tag::new("server".to_string())
+            .values(vec![Value::String("main".to_string())])
+            .tags(vec![
+                Tag::new("port".to_string()).values(vec![Value::from(80)])
+            ])]
+    );
+}
+
+proptest! {
+    #![proptest_config(tree_config())]
+
+    #[test]
+    fn tagtree(test in gen::tagtree()) {
+        let doc = test.text.parse::<Document>().unwrap();
+        assert_eq!(doc.root.kind, NodeKind::Document);
+        assert_eq!(doc.to_string(), test.text);
+        assert_eq!(doc.to_tag().unwrap().tags, test.result);
+    }
+}
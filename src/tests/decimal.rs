@@ -0,0 +1,82 @@
+use crate::{emit, emit_text, parse_text, BigDecimal, NumberKind, Value};
+
+use proptest::prelude::*;
+
+#[test]
+fn parse() {
+    let dec = |text: &str| text.parse::<BigDecimal>().unwrap();
+    assert_eq!(
+        dec("3.14159265358979323846").to_string(),
+        "3.14159265358979323846"
+    );
+    assert_eq!(dec("-007.50").to_string(), "-7.50");
+    assert_eq!(dec("-0.000").to_string(), "0.000");
+    assert_eq!(dec("12"), BigDecimal::from(12));
+    assert_eq!(dec("-0.05"), BigDecimal::new(-5, 2));
+    assert_eq!(dec("-0.05").scale(), 2);
+    assert!(dec("-0.05").is_negative());
+    assert_eq!(dec("2.5").to_f64(), 2.5);
+    assert_eq!(dec("-12").to_i128(), Some(-12));
+    assert_eq!(dec("12.0").to_i128(), None);
+    assert_eq!(dec(&format!("{}0", i128::MAX)).to_i128(), None);
+    // Trailing zeros are kept, as part of the precision.
+    assert_ne!(dec("1.50"), dec("1.5"));
+
+    for text in &["", "-", "1.", ".5", "1e5", "1.2.3", "+1", "1 "] {
+        assert!(text.parse::<BigDecimal>().is_err(), "{:?}", text);
+    }
+}
+
+#[test]
+fn values() {
+    let text = "a 3.14159265358979323846BD -0.10BD \
+                123456789012345678901234567890123456789012BD 5BD 1.5\n";
+    let root = parse_text(text).unwrap();
+    let dec = |text: &str| Value::BigDecimal(text.parse().unwrap());
+    assert_eq!(
+        root.tags[0].values,
+        [
+            dec("3.14159265358979323846"),
+            dec("-0.10"),
+            dec("123456789012345678901234567890123456789012"),
+            Value::Number(5, NumberKind::BigDecimal),
+            Value::from(1.5),
+        ]
+    );
+    assert_eq!(root.tags[0].values[1].to_string(), "-0.10BD");
+    assert_eq!(emit_text(&root).unwrap(), text);
+}
+
+/// Integral big decimals are read back as numbers of the `BigDecimal` kind,
+/// which `Value::from` gives for them as well.
+#[test]
+fn integers() {
+    let max = i128::MAX.to_string();
+    let huge = format!("{}0", max);
+    for text in &["0", "5", "-5", &max, &huge] {
+        let val = Value::from(text.parse::<BigDecimal>().unwrap());
+        let mut emitted = String::new();
+        emit::value(&mut emitted, &val).unwrap();
+        assert_eq!(emitted, format!("{}BD", text));
+        let root = parse_text(&format!("a {}", emitted)).unwrap();
+        assert_eq!(root.tags[0].values, [val]);
+    }
+    assert_eq!(
+        Value::from(BigDecimal::from(5)),
+        Value::Number(5, NumberKind::BigDecimal)
+    );
+    assert_eq!(
+        Value::from(huge.parse::<BigDecimal>().unwrap()),
+        Value::BigDecimal(huge.parse().unwrap())
+    );
+}
+
+proptest! {
+    #[test]
+    fn round_trip(text in "-?[0-9]{1,40}(\\.[0-9]{1,40})?") {
+        let dec = text.parse::<BigDecimal>().unwrap();
+        assert_eq!(dec.to_string().parse::<BigDecimal>().unwrap(), dec);
+        // Leading zeros are dropped, without changing the value.
+        assert_eq!(dec.to_f64(), text.parse::<f64>().unwrap());
+    }
+}
@@ -0,0 +1,77 @@
+use super::gen;
+use super::utils::tree_config;
+use crate::grammar::{self, Rule};
+use crate::{descent, parse, ParseOptions, Result, Tag};
+
+use proptest::prelude::*;
+
+/// Parses the text with the hand-written parser.
+fn handwritten(text: &str) -> Option<Vec<Tag>> {
+    descent::tagtree(text, &ParseOptions::default())
+}
+
+/// Parses the text with the Pest grammar.
+fn pest(text: &str) -> Result<Vec<Tag>> {
+    grammar::parse(Rule::tagtree, text)
+        .and_then(|tree| parse::tagtree(tree, &ParseOptions::default()))
+}
+
+#[test]
+fn values() {
+    let text = concat!(
+        "// A comment\n",
+        "ns:a \"esc\\\"aped\\n\" `raw\\n` [aGVs\n bG8=] 2019/01/02 \\\n",
+        "    2019/01/02 /* c */ 12:34:56.789-GMT+05:30 2019/01/02 12:34:56 \n",
+        "b 1d:02:03:04 02:03:04 -1.5 2.5f 3 4L -5BD true off null x=on {\n",
+        "    # Nested\n",
+        "    -- tags\n",
+        "    c; \"anonymous\"\n",
+        "\n",
+        "} /* after */\n",
+        "d:e\n",
+    );
+    let tags = handwritten(text).unwrap();
+    assert_eq!(tags, pest(text).unwrap());
+    assert_eq!(tags[1].values.len(), 10);
+    assert_eq!(tags[1].tags.len(), 2);
+}
+
+#[test]
+fn invalid() {
+    for text in &[
+        "a{}",
+        "a {",
+        "on:x",
+        "a on=1",
+        "a x=1 2",
+        "a 2019/02/30",
+        "a 3000000000",
+        "a 12:00:00-UTC",
+        "a /* open",
+        "}",
+    ] {
+        assert_eq!(handwritten(text), None, "{:?}", text);
+        assert!(pest(text).is_err(), "{:?}", text);
+    }
+}
+
+proptest! {
+    #[test]
+    fn same_as_pest(text in "[a-z0-9 \"{};\\[\\]=:/\\-#*\n\t`\\\\.]{0,64}") {
+        assert_eq!(handwritten(&text), pest(&text).ok());
+    }
+
+    #[test]
+    fn values_same_as_pest(text in "a [0-9 :/.\\-+dfLBDGJMSTUC_\n]{0,40}") {
+        assert_eq!(handwritten(&text), pest(&text).ok());
+    }
+}
+
+proptest! {
+    #![proptest_config(tree_config())]
+
+    #[test]
+    fn tagtree(test in gen::tagtree()) {
+        assert_eq!(handwritten(&test.text), Some(test.result));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::parse_text;
+
+/// Parses the text and renders the resulting error.
+fn render(text: &str) -> String {
+    let err = parse_text(text).unwrap_err();
+    err.diagnostic(text).name("test.sdl").to_string()
+}
+
+#[test]
+fn plain() {
+    assert_eq!(
+        render("a 1\nport 3000000000 // HTTP\n"),
+        concat!(
+            "error: Number too large for a number without a suffix\n",
+            " --> test.sdl:2:6\n",
+            "  |\n",
+            "2 | port 3000000000 // HTTP\n",
+            "  |      ^^^^^^^^^^\n",
+            "  = help: did you mean `3000000000L`? ",
+            "the value exceeds the 32-bit range\n",
+        )
+    );
+    assert_eq!(
+        render("a 1\nb 2\nc 3\nd 4\ne 5\nf 6\ng 7\nh 8\ni 9\n\tj\t\"text\n"),
+        concat!(
+            "error: Expected a value, an identifier or the end of the text\n",
+            "  --> test.sdl:10:4\n",
+            "   |\n",
+            "10 |     j    \"text\n",
+            "   |          ^\n",
+        )
+    );
+}
+
+#[test]
+fn help() {
+    let help = |text: &str| {
+        let err = parse_text(text).unwrap_err();
+        err.diagnostic(text).help()
+    };
+    assert_eq!(
+        help("a 10000000000000000000L").unwrap(),
+        "did you mean `10000000000000000000BD`? the value exceeds the 64-bit \
+         range"
+    );
+    assert!(help("a 2019/02/30").unwrap().contains("`2019/02/30`"));
+    assert!(help("a 24:00").unwrap().contains("`hh:mm`"));
+    assert!(help("a [A]").is_some());
+    assert!(help("a \"\\q\"").unwrap().contains("`\\uXXXX`"));
+    assert!(help("a \"\\ud83d\"").unwrap().contains("`\\ud83d`"));
+    assert!(help("a {").is_none());
+}
+
+#[test]
+fn color() {
+    let text = "a 2019/13/01";
+    let err = parse_text(text).unwrap_err();
+    let out = err.diagnostic(text).color(true).to_string();
+    assert!(out.starts_with("\x1b[1;31merror\x1b[0m"));
+    assert!(out.contains("\x1b[1;31m^^^^^^^^^^\x1b[0m"));
+    assert_eq!(err.diagnostic(text).to_string().find('\x1b'), None);
+}
@@ -0,0 +1,216 @@
+use super::gen;
+use super::utils::tree_config;
+use crate::{Document, EditError, Tag, Value};
+
+use proptest::prelude::*;
+
+/// Parses the text, applies the edit and checks the resulting text.
+fn check<F>(text: &str, edit: F, expected: &str)
+where
+    F: FnOnce(&mut Document) -> Result<(), EditError>,
+{
+    let mut doc = text.parse::<Document>().unwrap();
+    edit(&mut doc).unwrap();
+    assert_eq!(doc.to_string(), expected);
+    assert_eq!(doc.to_string().parse::<Document>().unwrap(), doc);
+}
+
+#[test]
+fn values() {
+    let text = "app \"web\" /* name */ 1 attr=true // trailing\n";
+    check(
+        text,
+        |doc| doc.set_value(&["app"], 1, &Value::from(2)),
+        "app \"web\" /* name */ 2 attr=true // trailing\n",
+    );
+    check(
+        text,
+        |doc| doc.insert_value(&["app"], 2, &Value::Null),
+        "app \"web\" /* name */ 1 null attr=true // trailing\n",
+    );
+    check(
+        text,
+        |doc| doc.insert_value(&["app"], 0, &Value::Boolean(false)),
+        "app false \"web\" /* name */ 1 attr=true // trailing\n",
+    );
+    check(
+        text,
+        |doc| doc.remove_value(&["app"], 1),
+        "app \"web\" /* name */ attr=true // trailing\n",
+    );
+    check("\"anonymous\" 1\n", |doc| doc.remove_value(&[""], 0), "1\n");
+}
+
+#[test]
+fn attrs() {
+    let text = "server port=80 host=\"a\" {\n    ssl on\n}\n";
+    check(
+        text,
+        |doc| doc.set_attr(&["server"], "host", &Value::from("b")),
+        "server port=80 host=\"b\" {\n    ssl on\n}\n",
+    );
+    check(
+        text,
+        |doc| doc.set_attr(&["server", "ssl"], "port", &Value::from(443)),
+        "server port=80 host=\"a\" {\n    ssl on port=443\n}\n",
+    );
+    check(
+        text,
+        |doc| doc.remove_attr(&["server"], "port"),
+        "server host=\"a\" {\n    ssl on\n}\n",
+    );
+}
+
+#[test]
+fn tags() {
+    let text = "# Servers\nserver {\n\tmain 1 // first\n}\nclient\n";
+    let tag =
+        Tag::new("backup".to_string()).tags(vec![Tag::new("ssl".to_string())]);
+    check(
+        text,
+        |doc| doc.insert_tag(&["server"], 1, &tag),
+        "# Servers\nserver {\n\tmain 1 // first\n\tbackup {\n\t\tssl\n\t}\n}\n\
+         client\n",
+    );
+    check(
+        text,
+        |doc| doc.insert_tag(&["server"], 0, &tag),
+        "# Servers\nserver {\n\tbackup {\n\t\tssl\n\t}\n\tmain 1 // first\n}\n\
+         client\n",
+    );
+    check(
+        text,
+        |doc| doc.insert_tag(&["client"], 0, &tag),
+        "# Servers\nserver {\n\tmain 1 // first\n}\nclient {\n    backup {\n\
+         \x20       ssl\n    }\n}\n",
+    );
+    check(
+        text,
+        |doc| doc.insert_tag(&[], 2, &Tag::new("extra".to_string())),
+        "# Servers\nserver {\n\tmain 1 // first\n}\nclient\nextra\n",
+    );
+    check(
+        text,
+        |doc| doc.remove_tag(&["server", "main"]),
+        "# Servers\nserver {\n}\nclient\n",
+    );
+    check("a; b; c", |doc| doc.remove_tag(&["b"]), "a; c");
+    check(
+        "a; b",
+        |doc| {
+            let tag = Tag::new("c".to_string()).namespace("ns".to_string());
+            doc.set_tag(&["b"], &tag)
+        },
+        "a; ns:c",
+    );
+}
+
+#[test]
+fn repeated() {
+    let text = "server 1 {\n    ssl on\n}\nserver 2 {\n    ssl on\n}\n";
+    check(
+        text,
+        |doc| doc.set_value(&["server[1]"], 0, &Value::from(3)),
+        "server 1 {\n    ssl on\n}\nserver 3 {\n    ssl on\n}\n",
+    );
+    check(
+        text,
+        |doc| doc.set_attr(&["server[1]", "ssl[0]"], "port", &Value::from(1)),
+        "server 1 {\n    ssl on\n}\nserver 2 {\n    ssl on port=1\n}\n",
+    );
+    check(
+        text,
+        |doc| doc.remove_tag(&["server[1]"]),
+        "server 1 {\n    ssl on\n}\n",
+    );
+    check(
+        text,
+        |doc| doc.remove_tag(&["server[0]", "ssl"]),
+        "server 1 {\n}\nserver 2 {\n    ssl on\n}\n",
+    );
+    let mut doc = text.parse::<Document>().unwrap();
+    assert_eq!(doc.remove_tag(&["server[2]"]), Err(EditError::NoTag));
+}
+
+#[test]
+fn durations() {
+    check(
+        "event 2019/01/02 \"late\"\n",
+        |doc| {
+            doc.set_value(&["event"], 1, &Value::Duration(Default::default()))
+        },
+        "event 2019/01/02 0d:00:00:00\n",
+    );
+}
+
+#[test]
+fn errors() {
+    let mut doc = "a 1; \"b\"".parse::<Document>().unwrap();
+    assert_eq!(
+        doc.set_value(&["x"], 0, &Value::Null),
+        Err(EditError::NoTag)
+    );
+    assert_eq!(doc.set_value(&[], 0, &Value::Null), Err(EditError::NoTag));
+    assert_eq!(doc.remove_value(&["a"], 1), Err(EditError::NoValue(1)));
+    assert_eq!(
+        doc.remove_attr(&["a"], "b"),
+        Err(EditError::NoAttr("b".to_string()))
+    );
+    assert_eq!(doc.remove_value(&[""], 0), Err(EditError::Unrepresentable));
+    assert_eq!(
+        doc.set_attr(&["a"], "null", &Value::Null),
+        Err(EditError::Unrepresentable)
+    );
+    assert_eq!(doc.to_string(), "a 1; \"b\"");
+}
+
+proptest! {
+    #![proptest_config(tree_config())]
+
+    #[test]
+    fn edits(
+        test in gen::tagtree(),
+        tag in gen::tag(),
+        value in gen::value(),
+        index in any::<prop::sample::Index>(),
+    ) {
+        let mut doc = test.text.parse::<Document>().unwrap();
+        let mut tags = test.result;
+
+        let index = index.index(tags.len() + 1);
+        doc.insert_tag(&[], index, &tag.result).unwrap();
+        tags.insert(index, tag.result.clone());
+
+        // Edit the first named tag, which is the one its path refers to.
+        let name = match tags.iter().find(|t| !t.name.is_empty()) {
+            Some(Tag { namespace: Some(ns), name, .. }) => {
+                format!("{}:{}", ns, name)
+            }
+            Some(t) => t.name.clone(),
+            None => return Ok(()),
+        };
+        let first = tags.iter_mut().find(|t| !t.name.is_empty()).unwrap();
+        let path = [name.as_str()];
+
+        doc.insert_value(&path, first.values.len(), &value.result).unwrap();
+        first.values.push(value.result.clone());
+        doc.set_attr(&path, "edited", &value.result).unwrap();
+        match first.attrs.iter_mut().find(|a| a.name == "edited") {
+            Some(attr) => attr.value = value.result.clone(),
+            None => first.attrs.push(
+                crate::Attribute::new("edited".to_string(), value.result),
+            ),
+        }
+        doc.insert_tag(&path, first.tags.len(), &tag.result).unwrap();
+        first.tags.push(tag.result);
+
+        let text = doc.to_string();
+        assert_eq!(doc.to_tag().unwrap().tags, tags);
+        assert_eq!(crate::parse_text(&text).unwrap().tags, tags);
+
+        doc.remove_tag(&path).unwrap();
+        let pos = tags.iter().position(|t| !t.name.is_empty()).unwrap();
+        tags.remove(pos);
+        assert_eq!(crate::parse_text(&doc.to_string()).unwrap().tags, tags);
+    }
+}
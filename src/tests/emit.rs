@@ -0,0 +1,170 @@
+use super::gen;
+use super::utils::{tree_config, RuleSet, Test};
+use crate::grammar::Rule;
+use crate::{emit, emit_text, parse, parse_text, ParseOptions, PrettyConfig};
+use crate::{DecimalKind, Duration, NumberKind, Value};
+
+use proptest::prelude::*;
+
+proptest! {
+    #[test]
+    fn string(test in gen::string()) {
+        let mut text = String::new();
+        emit::string(&mut text, &test.result).unwrap();
+        RuleSet::new(Rule::string, parse::string)
+            .test(Test::new(text, test.result))
+    }
+
+    #[test]
+    fn value(test in gen::value()) {
+        let mut text = String::new();
+        emit::value(&mut text, &test.result).unwrap();
+        RuleSet::new(Rule::value, |tree| {
+            parse::value(tree, &ParseOptions::default())
+        })
+        .test(Test::new(text, test.result))
+    }
+
+    #[test]
+    fn attribute(test in gen::attribute()) {
+        let mut text = String::new();
+        emit::attribute(&mut text, &test.result).unwrap();
+        RuleSet::new(Rule::attribute, |tree| {
+            parse::attribute(tree, &ParseOptions::default())
+        })
+        .test(Test::new(text, test.result))
+    }
+}
+
+proptest! {
+    #![proptest_config(tree_config())]
+
+    #[test]
+    fn tagtree(test in gen::tagtree()) {
+        let mut text = String::new();
+        emit::tags(&mut text, &test.result, &PrettyConfig::default(), 0)
+            .unwrap();
+        RuleSet::new(Rule::tagtree, |tree| {
+            parse::tagtree(tree, &ParseOptions::default())
+        })
+        .test(Test::new(text, test.result))
+    }
+
+    #[test]
+    fn tagtree_pretty(
+        test in gen::tagtree(),
+        config in gen::pretty_config(),
+    ) {
+        let mut text = String::new();
+        emit::tags(&mut text, &test.result, &config, 0).unwrap();
+        RuleSet::new(Rule::tagtree, |tree| {
+            parse::tagtree(tree, &ParseOptions::default())
+        })
+        .test(Test::new(text, test.result))
+    }
+}
+
+#[test]
+fn times() {
+    let root = parse_text(
+        "a 2015/12/06 12:00 12:00:00.5 00:00:00.000001 1d:00:00:00.12345678",
+    )
+    .unwrap();
+    // Seconds are always written, along with the whole fraction of a second.
+    assert_eq!(
+        emit_text(&root).unwrap(),
+        "a 2015/12/06 12:00:00 12:00:00.500 00:00:00.000001 \
+         1d:00:00:00.123456780\n"
+    );
+    // Leap seconds are kept as second 60.
+    let text = "a 2016/12/31 23:59:60.500-UTC\n";
+    assert_eq!(emit_text(&parse_text(text).unwrap()).unwrap(), text);
+}
+
+#[test]
+fn negative_durations() {
+    let text = "a -00:30:00 -2d:00:00:00.5 2019/01/02 -12:00:00\n";
+    let root = parse_text(text).unwrap();
+    assert_eq!(
+        root.tags[0].values[..2],
+        [
+            Value::Duration(Duration::minutes(-30)),
+            Value::Duration(-Duration::days(2) - Duration::milliseconds(500)),
+        ]
+    );
+    assert_eq!(emit_text(&root).unwrap(), text.replace(".5", ".500"));
+    // A negative number is still a number.
+    assert_eq!(
+        parse_text("a -12").unwrap().tags[0].values[0],
+        Value::from(-12)
+    );
+}
+
+#[test]
+fn number_kinds() {
+    let text = "a 5 5L 5BD 2147483648L\n";
+    let root = parse_text(text).unwrap();
+    assert_eq!(
+        root.tags[0].values,
+        [
+            Value::Number(5, NumberKind::Int),
+            Value::Number(5, NumberKind::Long),
+            Value::Number(5, NumberKind::BigDecimal),
+            Value::Number(1 << 31, NumberKind::Long),
+        ]
+    );
+    assert_eq!(root.tags[0].values[1].to_string(), "5L");
+    // The kind is kept when writing the numbers back.
+    assert_eq!(emit_text(&root).unwrap(), text);
+    // Numbers must be in the range of their kind.
+    let mut text = String::new();
+    assert!(emit::number(&mut text, 1 << 31, NumberKind::Int).is_err());
+    assert_eq!(
+        Value::from(1i128 << 31),
+        Value::Number(1 << 31, NumberKind::Long)
+    );
+}
+
+#[test]
+fn decimal_kinds() {
+    let root = parse_text("a 0.1f 0.1 0.1d 2.0f\n").unwrap();
+    assert_eq!(
+        root.tags[0].values,
+        [
+            Value::Decimal(0.1f32.into(), DecimalKind::Float),
+            Value::Decimal(0.1, DecimalKind::Double),
+            Value::Decimal(0.1, DecimalKind::Double),
+            Value::from(2.0f32),
+        ]
+    );
+    assert_eq!(root.tags[0].values[0].to_string(), "0.1f");
+    // Floats are written back as floats, and doubles without a suffix.
+    assert_eq!(emit_text(&root).unwrap(), "a 0.1f 0.1 0.1 2.0f\n");
+    // Floats must be exactly representable in 32 bits.
+    let mut text = String::new();
+    assert!(emit::decimal(&mut text, 0.1, DecimalKind::Float).is_err());
+}
+
+#[test]
+fn escapes() {
+    let text = "a \"\\u00e9\\uD83D\\uDE00 \\\n    b\\\r\n\tc\" '\\u0041'\n";
+    let root = parse_text(text).unwrap();
+    assert_eq!(
+        root.tags[0].values,
+        [Value::from("é😀 bc"), Value::from('A')]
+    );
+    assert_eq!(emit_text(&root).unwrap(), "a \"é😀 bc\" 'A'\n");
+}
+
+#[test]
+fn characters() {
+    let text = "a 'x' '\\n' '\\'' '\"' 'é'\n";
+    let root = parse_text(text).unwrap();
+    assert_eq!(
+        root.tags[0].values,
+        ['x', '\n', '\'', '"', 'é'].map(Value::from)
+    );
+    assert_eq!(emit_text(&root).unwrap(), text);
+    assert!(parse_text("a ''").is_err());
+    assert!(parse_text("a 'xy'").is_err());
+}
@@ -0,0 +1,114 @@
+use crate::{parse_file, parse_text, Error, Position};
+
+use std::io;
+
+/// Parses the text, returning the error and the position it was found at.
+fn fail(text: &str) -> (Error, Position) {
+    let err = parse_text(text).unwrap_err();
+    let pos = err.span().unwrap().start;
+    (err, pos)
+}
+
+#[test]
+fn syntax() {
+    match fail("tag \"text\n") {
+        (Error::Syntax { expected, .. }, pos) => {
+            assert_eq!((pos.line, pos.col), (1, 5));
+            assert!(expected.contains(&"a value"));
+        }
+        (err, _) => panic!("unexpected error: {:?}", err),
+    }
+    let (err, _) = fail("a {\n    b\n");
+    assert!(err.to_string().ends_with(" at 3:1"), "{}", err);
+}
+
+#[test]
+fn values() {
+    match fail("a 1 2019/02/30") {
+        (Error::InvalidDate { span }, pos) => {
+            assert_eq!(pos.col, 5);
+            assert_eq!(span.range(), 4..14);
+        }
+        (err, _) => panic!("unexpected error: {:?}", err),
+    }
+    match fail("a 2019/02/03 25:00:00") {
+        (Error::InvalidDate { .. }, pos) => assert_eq!(pos.col, 14),
+        (err, _) => panic!("unexpected error: {:?}", err),
+    }
+    match fail("a\nb 2019/02/03 12:00:00-GMT+25:00") {
+        (Error::InvalidTimezone { .. }, pos) => {
+            assert_eq!((pos.line, pos.col), (2, 26))
+        }
+        (err, _) => panic!("unexpected error: {:?}", err),
+    }
+    match fail("a 3000000000") {
+        (Error::NumberOutOfRange { suffix, .. }, _) => assert_eq!(suffix, ""),
+        (err, _) => panic!("unexpected error: {:?}", err),
+    }
+    match fail("a 10000000000000000000L") {
+        (Error::NumberOutOfRange { suffix, span }, _) => {
+            assert_eq!(suffix, "L");
+            assert_eq!(span.range(), 2..22);
+        }
+        (err, _) => panic!("unexpected error: {:?}", err),
+    }
+    match fail("a 99999999999d:00:00:00") {
+        (Error::NumberOutOfRange { suffix, .. }, _) => assert_eq!(suffix, "d"),
+        (err, _) => panic!("unexpected error: {:?}", err),
+    }
+    match fail("a [A]") {
+        (Error::InvalidBase64 { .. }, pos) => assert_eq!(pos.col, 3),
+        (err, _) => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn escapes() {
+    let escape = |text: &str| match parse_text(text).unwrap_err() {
+        Error::InvalidEscape { span } => (
+            text[span.range()].to_string(),
+            (span.start.line, span.start.col),
+        ),
+        err => panic!("unexpected error: {:?}", err),
+    };
+    assert_eq!(escape("a \"\\q\""), ("\\q".into(), (1, 4)));
+    assert_eq!(escape("a \"é\\\n  \\é\""), ("\\é".into(), (2, 3)));
+    assert_eq!(escape("a \"\\u00g0\""), ("\\u".into(), (1, 4)));
+    assert_eq!(escape("a \"\\ud83d\\u0041\""), ("\\ud83d".into(), (1, 4)));
+    assert_eq!(escape("a \"\\ude00\""), ("\\ude00".into(), (1, 4)));
+    assert_eq!(escape("a '\\q'"), ("\\q".into(), (1, 4)));
+    assert_eq!(escape("a '\\\n'"), ("\\\n".into(), (1, 4)));
+}
+
+#[test]
+fn io() {
+    struct Broken;
+    impl io::Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("broken"))
+        }
+    }
+
+    let err = parse_file(Broken).unwrap_err();
+    assert!(err.span().is_none());
+    assert!(std::error::Error::source(&err).is_some());
+    match err {
+        Error::Io(err) => assert_eq!(err.to_string(), "broken"),
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[cfg(not(feature = "bigdecimal"))]
+#[test]
+fn big_decimals_need_feature() {
+    let text = "a 1 3.14159BD";
+    let err = parse_text(text).unwrap_err();
+    match err {
+        Error::UnsupportedBigDecimal { span } => {
+            assert_eq!(&text[span.range()], "3.14159BD")
+        }
+        ref err => panic!("unexpected error: {:?}", err),
+    }
+    let help = err.diagnostic(text).help().unwrap();
+    assert!(help.contains("`bigdecimal` feature"));
+}
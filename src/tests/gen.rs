@@ -3,12 +3,14 @@
 //! Each generator corresponds to a different test.
 
 use super::utils::*;
-use crate::types::{Attribute, DateTime, Tag, Value};
+use crate::types::{Attribute, DecimalKind, Duration, NumberKind, Tag};
+use crate::types::{Timezone, Value};
+use crate::{BraceStyle, PrettyConfig};
 
 use base64 as b64;
 
-use chrono::{Datelike, NaiveDate, NaiveTime};
-use chrono::{Local, Offset, TimeZone, Utc};
+use chrono::{Datelike, NaiveDate, NaiveTime, Timelike};
+use chrono::{FixedOffset, TimeZone};
 
 use itertools::EitherOrBoth;
 use itertools::Itertools;
@@ -16,58 +18,85 @@ use itertools::Itertools;
 use proptest::prelude::*;
 use proptest::strategy::ValueTree;
 
-use std::time::Duration;
+/// Creates an escape strategy, giving the text of an escape and the
+/// character it stands for.
+fn escape() -> impl Strategy<Value = (String, char)> {
+    let simple = vec![
+        ('n', '\n'),
+        ('r', '\r'),
+        ('t', '\t'),
+        ('0', '\x00'),
+        ('\\', '\\'),
+        ('"', '"'),
+        ('\'', '\''),
+    ];
+    prop_oneof![
+        prop::sample::select(simple)
+            .prop_map(|(esc, ch)| (format!("\\{}", esc), ch)),
+        // Characters outside the BMP are written as surrogate pairs.
+        any::<char>().prop_map(|ch| {
+            let text = ch
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|unit| format!("\\u{:04x}", unit))
+                .collect();
+            (text, ch)
+        }),
+    ]
+}
 
 /// Creates a string test.
 pub fn string(
 ) -> impl Strategy<Value = Test<String>, Tree = impl ValueTree<Value = Test<String>>>
 {
-    ("[^\\\\]*", ".*", prop::bool::ANY).prop_map(|(text, escapes, raw)| {
-        let ch = if raw { '`' } else { '"' };
-        let input = text
-            .chars()
-            .filter(|c| *c != ch)
-            .chunks(2)
-            .into_iter()
-            .zip_longest(escapes.chars().filter(|c| if raw {
-                *c != '`'
-            } else {
-                true
-            }))
-            .format_with("", |e, f| match e {
-                EitherOrBoth::Left(c) => f(&c.format("")),
-                EitherOrBoth::Right(e) => f(&format_args!("\\{}", e)),
-                EitherOrBoth::Both(c, e) => {
-                    f(&format_args!("{}\\{}", c.format(""), e))
+    ("[^\\\\]*", prop::collection::vec(escape(), 0..16), prop::bool::ANY)
+        .prop_map(|(text, escapes, raw)| {
+            let quote = if raw { '`' } else { '"' };
+            let (mut input, mut value) = (String::new(), String::new());
+            for part in text
+                .chars()
+                .filter(|c| *c != quote)
+                .chunks(2)
+                .into_iter()
+                .zip_longest(escapes)
+            {
+                let (chunk, escape) = match part {
+                    EitherOrBoth::Left(c) => (Some(c), None),
+                    EitherOrBoth::Right(e) => (None, Some(e)),
+                    EitherOrBoth::Both(c, e) => (Some(c), Some(e)),
+                };
+                for c in chunk.into_iter().flatten() {
+                    input.push(c);
+                    value.push(c);
                 }
-            })
-            .to_string();
-        Test::new(
-            format!("{0:}{1:}{0:}", ch, input),
-            if raw {
-                input
-            } else {
-                text.chars()
-                    .filter(|c| *c != ch)
-                    .chunks(2)
-                    .into_iter()
-                    .zip_longest(escapes.chars().map(|ch| match ch {
-                        'n' => '\n',
-                        'r' => '\r',
-                        't' => '\t',
-                        '0' => '\x00',
-                        chr => chr,
-                    }))
-                    .format_with("", |e, f| match e {
-                        EitherOrBoth::Left(c) => f(&c.format("")),
-                        EitherOrBoth::Right(e) => f(&format_args!("{}", e)),
-                        EitherOrBoth::Both(c, e) => {
-                            f(&format_args!("{}{}", c.format(""), e))
-                        }
-                    })
-                    .to_string()
-            },
-        )
+                if let Some((esc, ch)) = escape {
+                    input.push_str(&esc);
+                    // Raw strings have no escapes.
+                    if raw {
+                        value.push_str(&esc);
+                    } else {
+                        value.push(ch);
+                    }
+                }
+            }
+            Test::new(format!("{0:}{1:}{0:}", quote, input), value)
+        })
+}
+
+/// Creates a character test.
+pub fn character(
+) -> impl Strategy<Value = Test<char>, Tree = impl ValueTree<Value = Test<char>>>
+{
+    any::<char>().prop_map(|ch| {
+        let text = match ch {
+            '\n' => "\\n".to_string(),
+            '\r' => "\\r".to_string(),
+            '\t' => "\\t".to_string(),
+            '\x00' => "\\0".to_string(),
+            '\'' | '\\' => format!("\\{}", ch),
+            c => c.to_string(),
+        };
+        Test::new(format!("'{}'", text), ch)
     })
 }
 
@@ -76,71 +105,115 @@ pub fn date() -> impl Strategy<
     Value = Test<NaiveDate>,
     Tree = impl ValueTree<Value = Test<NaiveDate>>,
 > {
-    (0i32..10_000, 1u32..=12)
+    // The day before the first of the month may fall into the previous year.
+    (1i32..10_000, 1u32..=12)
         .prop_flat_map(|(y, m)| {
-            let day = NaiveDate::from_ymd(y, m, 1).pred();
+            let day = NaiveDate::from_ymd_opt(y, m, 1).unwrap().pred_opt().unwrap();
             (Just(day.year()), Just(day.month()), 1..=day.day())
         })
         .prop_map(|(y, m, d)| {
             Test::new(
                 format!("{:04}/{:02}/{:02}", y, m, d),
-                NaiveDate::from_ymd(y, m, d),
+                NaiveDate::from_ymd_opt(y, m, d).unwrap(),
             )
         })
 }
 
-/// Creates a time test.
+/// Creates the fraction of a second of a time, in nanoseconds.
+pub fn fraction(
+) -> impl Strategy<Value = Test<u32>, Tree = impl ValueTree<Value = Test<u32>>>
+{
+    prop_oneof![
+        Just(Test::new(String::new(), 0)),
+        (1usize..10, 0u32..1_000_000_000).prop_map(|(len, nanos)| {
+            let digits = &format!("{:09}", nanos)[..len];
+            Test::new(
+                format!(".{}", digits),
+                digits.parse::<u32>().unwrap() * 10u32.pow(9 - len as u32),
+            )
+        }),
+    ]
+}
+
+/// Creates a time test, with optional seconds.
 pub fn time() -> impl Strategy<
     Value = Test<NaiveTime>,
     Tree = impl ValueTree<Value = Test<NaiveTime>>,
 > {
-    prop::bool::ANY
-        .prop_flat_map(|with_ms| {
-            (
-                Just(with_ms),
-                (0u32..24, 0u32..60, 0u32..60, 0u32..1000).prop_map(
-                    move |(h, m, s, ms)| {
-                        if with_ms {
-                            NaiveTime::from_hms_milli(h, m, s, ms)
-                        } else {
-                            NaiveTime::from_hms(h, m, s)
-                        }
-                    },
-                ),
-            )
-        })
-        .prop_map(|(with_ms, time)| {
+    (0u32..24, 0u32..60, prop::option::of((0u32..60, fraction()))).prop_map(
+        |(h, m, secs)| match secs {
+            Some((s, frac)) => Test::new(
+                format!("{:02}:{:02}:{:02}{}", h, m, s, frac.text),
+                NaiveTime::from_hms_nano_opt(h, m, s, frac.result).unwrap(),
+            ),
+            None => Test::new(
+                format!("{:02}:{:02}", h, m),
+                NaiveTime::from_hms_opt(h, m, 0).unwrap(),
+            ),
+        },
+    )
+}
+
+/// A timezone, with its offset in minutes (`None` for no timezone).
+type Zone = Option<(i32, Timezone)>;
+
+/// Creates a timezone test.
+pub fn timezone(
+) -> impl Strategy<Value = Test<Zone>, Tree = impl ValueTree<Value = Test<Zone>>>
+{
+    prop_oneof![
+        Just(Test::new(String::new(), None)),
+        Just(Test::new("-UTC".into(), Some((0, Timezone::Utc)))),
+        Just(Test::new("-GMT".into(), Some((0, Timezone::Gmt)))),
+        (prop::bool::ANY, 0i32..24, prop::option::of(0i32..60)).prop_map(
+            |(neg, h, m)| {
+                let sign = if neg { -1 } else { 1 };
+                Test::new(
+                    format!(
+                        "-GMT{}{:02}{}",
+                        if neg { '-' } else { '+' },
+                        h,
+                        m.map_or(String::new(), |m| format!(":{:02}", m))
+                    ),
+                    Some((sign * (h * 60 + m.unwrap_or(0)), Timezone::Gmt)),
+                )
+            }
+        ),
+        prop::sample::select(vec![
+            ("JST", 9 * 60),
+            ("PST", -8 * 60),
+            ("IST", 5 * 60 + 30),
+        ])
+        .prop_map(|(name, mins)| {
             Test::new(
-                time.format(if with_ms { "%H:%M:%S%.3f" } else { "%H:%M:%S" })
-                    .to_string(),
-                time,
+                format!("-{}", name),
+                Some((mins, Timezone::Named(name.to_string()))),
             )
-        })
+        }),
+    ]
 }
 
 /// Creates a datetime test.
 pub fn datetime() -> impl Strategy<
-    Value = Test<DateTime>,
-    Tree = impl ValueTree<Value = Test<DateTime>>,
+    Value = Test<Value>,
+    Tree = impl ValueTree<Value = Test<Value>>,
 > {
-    (date(), time(), "[ \t]+", prop::bool::ANY).prop_map(
-        |(date, time, white, utc)| {
-            Test::new(
-                format!(
-                    "{}{}{}{}",
-                    date.text,
-                    white,
-                    time.text,
-                    if utc { "-UTC" } else { "" }
+    (date(), time(), "[ \t]+", timezone()).prop_map(
+        |(date, time, white, zone)| {
+            let naive = date.result.and_time(time.result);
+            let dtime = match zone.result {
+                Some((mins, zone)) => Value::DateTime(
+                    FixedOffset::east_opt(mins * 60)
+                        .unwrap()
+                        .from_local_datetime(&naive)
+                        .unwrap(),
+                    zone,
                 ),
-                if utc {
-                    Utc.fix()
-                        .from_utc_datetime(&date.result.and_time(time.result))
-                } else {
-                    let naive = date.result.and_time(time.result);
-                    let local = Local.from_local_datetime(&naive).unwrap();
-                    local.with_timezone(local.offset())
-                },
+                None => Value::NaiveDateTime(naive),
+            };
+            Test::new(
+                format!("{}{}{}{}", date.text, white, time.text, zone.text),
+                dtime,
             )
         },
     )
@@ -153,68 +226,57 @@ pub fn duration() -> impl Strategy<
 > {
     (
         prop::bool::ANY,
-        prop::bool::ANY,
-        0u64..24,
-        0u64..60,
-        0u64..60,
-        prop::num::u32::ANY,
-        0u32..1000,
+        prop::option::of(prop::num::u32::ANY),
+        time(),
     )
-        .prop_map(|(with_d, with_ms, h, m, s, d, ms)| {
+        .prop_map(|(negative, days, time)| {
+            let dur = Duration::days(days.map_or(0, i64::from))
+                + Duration::seconds(
+                    time.result.num_seconds_from_midnight().into(),
+                )
+                + Duration::nanoseconds(time.result.nanosecond().into());
             Test::new(
                 format!(
-                    "{}{:02}:{:02}:{:02}{}",
-                    if with_d {
-                        format!("{}d:", d)
-                    } else {
-                        String::new()
-                    },
-                    h,
-                    m,
-                    s,
-                    if with_ms {
-                        format!(".{:03}", ms)
-                    } else {
-                        String::new()
-                    }
-                ),
-                Duration::new(
-                    s + 60
-                        * (m + 60
-                            * (h + 24 * u64::from(if with_d { d } else { 0 }))),
-                    1_000_000 * if with_ms { ms } else { 0 },
+                    "{}{}{}",
+                    if negative { "-" } else { "" },
+                    days.map_or(String::new(), |d| format!("{}d:", d)),
+                    time.text
                 ),
+                if negative { -dur } else { dur },
             )
         })
 }
 
 /// Creates a number test.
-pub fn number(
-) -> impl Strategy<Value = Test<i128>, Tree = impl ValueTree<Value = Test<i128>>>
-{
+pub fn number() -> impl Strategy<
+    Value = Test<(i128, NumberKind)>,
+    Tree = impl ValueTree<Value = Test<(i128, NumberKind)>>,
+> {
     (prop::num::i128::ANY, 0u8..3).prop_map(|(n, s)| {
-        let (n, suf) = match s {
-            0 => (i128::from(n as i32), ""),
-            1 => (i128::from(n as i64), "L"),
-            2 => (n, "BD"),
+        let (n, kind) = match s {
+            0 => (i128::from(n as i32), NumberKind::Int),
+            1 => (i128::from(n as i64), NumberKind::Long),
+            2 => (n, NumberKind::BigDecimal),
             _ => unreachable!(),
         };
-        Test::new(format!("{}{}", n, suf), n)
+        Test::new(format!("{}{}", n, kind.suffix()), (n, kind))
     })
 }
 
 /// Creates a decimal test.
-pub fn decimal(
-) -> impl Strategy<Value = Test<f64>, Tree = impl ValueTree<Value = Test<f64>>>
-{
-    (-1e20f64..1e20, 1..std::f64::DIGITS).prop_map(|(n, s)| {
+pub fn decimal() -> impl Strategy<
+    Value = Test<(f64, DecimalKind)>,
+    Tree = impl ValueTree<Value = Test<(f64, DecimalKind)>>,
+> {
+    (-1e20f64..1e20, 1..f64::DIGITS, 0u8..3).prop_map(|(n, s, k)| {
         let text = format!("{:.*}", s as usize, n);
-        let (n, suf) = if s < std::f32::DIGITS {
-            (text.parse::<f32>().unwrap() as f64, "f")
-        } else {
-            (text.parse::<f64>().unwrap(), "")
+        let (n, suf, kind) = match k {
+            0 => (text.parse::<f32>().unwrap().into(), "f", DecimalKind::Float),
+            1 => (text.parse().unwrap(), "", DecimalKind::Double),
+            2 => (text.parse().unwrap(), "d", DecimalKind::Double),
+            _ => unreachable!(),
         };
-        Test::new(format!("{:.*}{}", s as usize, n, suf), n)
+        Test::new(format!("{}{}", text, suf), (n, kind))
     })
 }
 
@@ -244,7 +306,7 @@ pub fn base64(
         .prop_flat_map(|size| {
             (
                 prop::collection::vec(prop::num::u8::ANY, size),
-                prop::collection::vec("[ \t]*", (size + 2) / 3),
+                prop::collection::vec("[ \t]*", size.div_ceil(3)),
             )
         })
         .prop_map(|(bytes, whites)| {
@@ -280,12 +342,17 @@ pub fn value(
     prop_oneof![
         null().prop_map(|test| test.map_res(|()| Value::Null)),
         boolean().prop_map(|test| test.map_res(Value::from)),
-        number().prop_map(|test| test.map_res(Value::from)),
-        decimal().prop_map(|test| test.map_res(Value::from)),
+        number().prop_map(|test| {
+            test.map_res(|(num, kind)| Value::Number(num, kind))
+        }),
+        decimal().prop_map(|test| {
+            test.map_res(|(dec, kind)| Value::Decimal(dec, kind))
+        }),
         date().prop_map(|test| test.map_res(Value::from)),
-        datetime().prop_map(|test| test.map_res(Value::from)),
+        datetime(),
         duration().prop_map(|test| test.map_res(Value::from)),
         string().prop_map(|test| test.map_res(Value::from)),
+        character().prop_map(|test| test.map_res(Value::from)),
         base64().prop_map(|test| test.map_res(Value::from)),
     ]
 }
@@ -322,6 +389,10 @@ pub fn namespace(
     ident().prop_map(|test| test.map_text(|text| text + ":"))
 }
 
+/// The separator between tags, which may contain comments.
+const TAG_SEP: &str =
+    "[ \t]*(/\\*[^*]*\\*/[ \t]*)?(;|((//|--|#)[^\n]*)?\n)[ \t]*";
+
 /// Creates a (recursive!) tag strategy.
 pub fn tag(
 ) -> impl Strategy<Value = Test<Tag>, Tree = impl ValueTree<Value = Test<Tag>>>
@@ -329,7 +400,7 @@ pub fn tag(
     tag_minimal().prop_recursive(8, 256, 8, |elem| {
         (
             tag_minimal(),
-            prop::collection::vec((elem, "[ \t]*[;\n][ \t]*"), 0..8),
+            prop::collection::vec((elem, TAG_SEP), 0..8),
             "[ \t]+",
         )
             .prop_map(|(tag, elems, white)| {
@@ -354,11 +425,21 @@ fn tag_minimal(
 {
     (
         prop::bool::ANY, prop::option::of(namespace()), ident(), "[ \t]+",
-        prop::collection::vec((value(), "[ \t]+"), 0..32),
+        prop::collection::vec((value(), "[ \t]+"), 0..32).prop_filter(
+            "A date followed by a time reads as a datetime",
+            |values| !values.windows(2).any(|pair| {
+                match (&pair[0].0.result, &pair[1].0.result) {
+                    (Value::Date(_), Value::Duration(_)) => {
+                        !pair[1].0.text.contains('d')
+                    }
+                    _ => false,
+                }
+            }),
+        ),
         prop::collection::vec((attribute(), "[ \t]+"), 0..32),
     )
     .prop_map(|(use_name, namespace, name, white, values, attributes)| {
-        let name = if values.len() == 0 || use_name {
+        let name = if values.is_empty() || use_name {
             let namespace = namespace
                 .map_or(Test::new(String::new(), None), |namespace| {
                     namespace.map_res(Some)
@@ -393,7 +474,7 @@ pub fn tagtree() -> impl Strategy<
     Value = Test<Vec<Tag>>,
     Tree = impl ValueTree<Value = Test<Vec<Tag>>>,
 > {
-    prop::collection::vec((tag(), "[ \t]*[;\n][ \t]*"), 0..8).prop_map(|tags| {
+    prop::collection::vec((tag(), TAG_SEP), 0..8).prop_map(|tags| {
         Test::new(
             tags.iter()
                 .format_with("", |(t, w), f| {
@@ -404,3 +485,28 @@ pub fn tagtree() -> impl Strategy<
         )
     })
 }
+
+/// Creates a pretty-printing configuration strategy.
+pub fn pretty_config() -> impl Strategy<Value = PrettyConfig> {
+    (
+        "[ \t]{0,4}",
+        0usize..120,
+        prop::bool::ANY,
+        prop::bool::ANY,
+        prop::bool::ANY,
+        0usize..3,
+    )
+        .prop_map(|(indent, width, wrap, next_line, per_line, spacing)| {
+            PrettyConfig::new()
+                .indent(indent)
+                .max_width(width)
+                .wrap(wrap)
+                .brace_style(if next_line {
+                    BraceStyle::NextLine
+                } else {
+                    BraceStyle::SameLine
+                })
+                .attr_per_line(per_line)
+                .top_level_spacing(spacing)
+        })
+}
@@ -0,0 +1,90 @@
+use crate::{parse_text, parse_text_recovering, parse_text_with_spans};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use std::cell::RefCell;
+
+thread_local! {
+    // Tests run in parallel, so each thread keeps its own events, and only
+    // while recording.
+    static EVENTS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Records the parse events logged on the current thread.
+struct Recorder;
+
+impl Log for Recorder {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Trace
+            && EVENTS.with(|events| events.borrow().is_some())
+    }
+
+    fn log(&self, record: &Record) {
+        if record.target() == "sdlang::parse" {
+            EVENTS.with(|events| {
+                if let Some(events) = events.borrow_mut().as_mut() {
+                    events.push(record.args().to_string());
+                }
+            });
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static RECORDER: Recorder = Recorder;
+
+/// Runs the given function, returning the parse events it logged.
+fn events<F: FnOnce()>(f: F) -> Vec<String> {
+    // Another test may have installed the logger already. The level is global,
+    // so it is left at `Trace` rather than being reset while another test
+    // still records its events.
+    let _ = log::set_logger(&RECORDER);
+    log::set_max_level(LevelFilter::Trace);
+    EVENTS.with(|events| *events.borrow_mut() = Some(Vec::new()));
+    f();
+    EVENTS.with(|events| events.borrow_mut().take().unwrap())
+}
+
+#[test]
+fn parse() {
+    let text = "ns:a 1 x=true {\n    \"b\"\n}";
+    let expected = vec![
+        "a tag at 1:1-3:2",
+        "a namespace `ns:` at 1:1-1:4",
+        "an identifier `a` at 1:4-1:5",
+        "a value `1` at 1:6-1:7",
+        "an attribute `x=true` at 1:8-1:14",
+        "a tag at 2:5-2:8",
+        "a value `\"b\"` at 2:5-2:8",
+    ];
+    assert_eq!(
+        events(|| {
+            parse_text(text).unwrap();
+        }),
+        expected
+    );
+    assert_eq!(
+        events(|| {
+            parse_text_with_spans(text).unwrap();
+        }),
+        expected
+    );
+}
+
+#[test]
+fn recovering() {
+    let events = events(|| {
+        parse_text_recovering("a @\nb 2 {\n    c\n}");
+    });
+    assert_eq!(
+        events,
+        vec![
+            "a tag at 2:1-2:6",
+            "an identifier `b` at 2:1-2:2",
+            "a value `2` at 2:3-2:4",
+            "a tag at 3:5-3:6",
+            "an identifier `c` at 3:5-3:6",
+        ]
+    );
+}
@@ -1,3 +1,21 @@
+pub mod borrowed;
+pub mod cst;
+#[cfg(feature = "bigdecimal")]
+pub mod decimal;
+#[cfg(feature = "handwritten")]
+pub mod descent;
+pub mod diagnostic;
+pub mod edit;
+pub mod emit;
+pub mod error;
 pub mod gen;
+#[cfg(feature = "log")]
+pub mod log;
 pub mod parse;
+pub mod recover;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod span;
+pub mod stream;
 pub mod utils;
+pub mod zone;
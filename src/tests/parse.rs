@@ -1,7 +1,7 @@
 use super::gen;
 use super::utils::RuleSet;
 use crate::grammar::Rule;
-use crate::parse;
+use crate::{parse, ParseOptions, Value};
 
 use proptest::prelude::*;
 
@@ -11,6 +11,11 @@ proptest! {
         RuleSet::new(Rule::string, parse::string).test(test)
     }
 
+    #[test]
+    fn character(test in gen::character()) {
+        RuleSet::new(Rule::character, parse::character).test(test)
+    }
+
     #[test]
     fn date(test in gen::date()) {
         RuleSet::new(Rule::date, parse::date).test(test)
@@ -23,7 +28,10 @@ proptest! {
 
     #[test]
     fn datetime(test in gen::datetime()) {
-        RuleSet::new(Rule::datetime, parse::datetime).test(test)
+        RuleSet::new(Rule::datetime, |tree| {
+            parse::datetime(tree, &ParseOptions::default())
+        })
+        .test(test)
     }
 
     #[test]
@@ -33,12 +41,14 @@ proptest! {
 
     #[test]
     fn number(test in gen::number()) {
-        RuleSet::new(Rule::number, parse::number).test(test)
+        RuleSet::new(Rule::number, parse::number)
+            .test(test.map_res(|(num, kind)| Value::Number(num, kind)))
     }
 
     #[test]
     fn decimal(test in gen::decimal()) {
-        RuleSet::new(Rule::decimal, parse::decimal).test(test)
+        RuleSet::new(Rule::decimal, parse::decimal)
+            .test(test.map_res(|(dec, kind)| Value::Decimal(dec, kind)))
     }
 
     #[test]
@@ -53,7 +63,10 @@ proptest! {
 
     #[test]
     fn value(test in gen::value()) {
-        RuleSet::new(Rule::value, parse::value).test(test)
+        RuleSet::new(Rule::value, |tree| {
+            parse::value(tree, &ParseOptions::default())
+        })
+        .test(test)
     }
 
     #[test]
@@ -68,16 +81,25 @@ proptest! {
 
     #[test]
     fn attribute(test in gen::attribute()) {
-        RuleSet::new(Rule::attribute, parse::attribute).test(test)
+        RuleSet::new(Rule::attribute, |tree| {
+            parse::attribute(tree, &ParseOptions::default())
+        })
+        .test(test)
     }
 
     #[test]
     fn tag(test in gen::tag()) {
-        RuleSet::new(Rule::tag, parse::tag).test(test)
+        RuleSet::new(Rule::tag, |tree| {
+            parse::tag(tree, &ParseOptions::default())
+        })
+        .test(test)
     }
 
     #[test]
     fn tagtree(test in gen::tagtree()) {
-        RuleSet::new(Rule::tagtree, parse::tagtree).test(test)
+        RuleSet::new(Rule::tagtree, |tree| {
+            parse::tagtree(tree, &ParseOptions::default())
+        })
+        .test(test)
     }
 }
@@ -0,0 +1,117 @@
+use super::gen;
+use super::utils::{in_zone, tree_config};
+use crate::recover::MAX_DEPTH;
+use crate::{parse_text, parse_text_recovering, Error, Tag, Value};
+use crate::{parse_text_recovering_with, parse_text_with};
+use crate::{DefaultZone, ParseOptions};
+
+use proptest::prelude::*;
+
+/// Returns the line and column at which each error starts.
+fn positions(errors: &[Error]) -> Vec<(usize, usize)> {
+    errors
+        .iter()
+        .map(|err| {
+            let pos = err.span().unwrap().start;
+            (pos.line, pos.col)
+        })
+        .collect()
+}
+
+#[test]
+fn errors() {
+    let text = concat!(
+        "a 1 =\n",
+        "b 2; c 2019/02/30 3000000000\n",
+        "d {\n",
+        "    e @ { f 1 }; g 1\n",
+        "    h \"}\" {\n",
+        "}\n",
+        "} i\n",
+        "j 1\n",
+        "k {\n",
+    );
+    let (root, errors) = parse_text_recovering(text);
+    assert_eq!(
+        positions(&errors),
+        vec![(1, 5), (2, 8), (2, 19), (4, 7), (7, 3), (10, 1)]
+    );
+    match &errors[2] {
+        Error::NumberOutOfRange { suffix, .. } => assert_eq!(suffix, ""),
+        err => panic!("unexpected error: {:?}", err),
+    }
+
+    let tag = |name: &str, val: i128| {
+        Tag::new(name.to_string()).values(vec![Value::from(val)])
+    };
+    assert_eq!(
+        root.tags,
+        vec![
+            tag("b", 2),
+            Tag::new("d".to_string()).tags(vec![
+                tag("g", 1),
+                Tag::new("h".to_string()).values(vec!["}".into()])
+            ]),
+            tag("j", 1),
+            Tag::new("k".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn nesting() {
+    let text = "a {\n".repeat(100_000);
+    let (root, errors) = parse_text_recovering(&text);
+    assert_eq!(positions(&errors[..1]), vec![(MAX_DEPTH + 1, 1)]);
+    match &errors[0] {
+        Error::NestingTooDeep { .. } => {}
+        err => panic!("unexpected error: {:?}", err),
+    }
+    let depth =
+        std::iter::successors(root.tags.first(), |tag| tag.tags.first())
+            .count();
+    assert_eq!(depth, MAX_DEPTH);
+}
+
+/// Checks a local time skipped by daylight saving time.
+#[test]
+fn local_gap() {
+    if !in_zone("tests::recover::local_gap", "America/Los_Angeles") {
+        return;
+    }
+    let options = ParseOptions::new().default_zone(DefaultZone::Local);
+    let text = "a 2019/03/10 02:30:00\nb 1";
+    let (root, errors) = parse_text_recovering_with(text, &options);
+    assert_eq!(positions(&errors), vec![(1, 3)]);
+    match &errors[0] {
+        Error::NonexistentLocalTime { .. } => {}
+        err => panic!("unexpected error: {:?}", err),
+    }
+    assert_eq!(root.tags.len(), 1);
+    assert!(parse_text_with("a 2019/03/10 02:30:00", &options).is_err());
+}
+
+proptest! {
+    #[test]
+    fn never_panics(text in "[a-z0-9 \"{};\\[\\]=:/\\-#*\n\t`\\\\.]{0,64}") {
+        let (root, errors) = parse_text_recovering(&text);
+        match parse_text(&text) {
+            Ok(tag) => {
+                assert_eq!(root, tag);
+                assert!(errors.is_empty());
+            }
+            Err(_) => assert!(!errors.is_empty()),
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(tree_config())]
+
+    #[test]
+    fn tagtree(test in gen::tagtree()) {
+        let (root, errors) = parse_text_recovering(&test.text);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(root.tags, test.result);
+    }
+}
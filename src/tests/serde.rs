@@ -0,0 +1,247 @@
+use crate::ser::Error as SerError;
+use crate::NaiveDateTime;
+use crate::{from_str, parse_text, to_string, Date, DateTime, Tag, Value};
+
+use ::serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Config {
+    name: String,
+    port: u16,
+    debug: Option<bool>,
+    ratio: f64,
+    tags: Vec<String>,
+    matrix: Vec<Vec<i32>>,
+    servers: Vec<Server>,
+    shapes: Vec<Shape>,
+    limits: BTreeMap<String, i64>,
+    timeout: Duration,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Server {
+    host: String,
+    port: u16,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Shape {
+    Point,
+    Circle(f64),
+    Line(i32, i32),
+    Rect { w: i32, h: i32 },
+}
+
+fn config() -> Config {
+    Config {
+        name: "app".to_string(),
+        port: 8080,
+        debug: None,
+        ratio: 0.5,
+        tags: vec!["a".to_string(), "b".to_string()],
+        matrix: vec![vec![1, 0], vec![0, 1]],
+        servers: vec![
+            Server {
+                host: "a.example.org".to_string(),
+                port: 1,
+            },
+            Server {
+                host: "b.example.org".to_string(),
+                port: 2,
+            },
+        ],
+        shapes: vec![
+            Shape::Point,
+            Shape::Circle(1.5),
+            Shape::Line(1, 2),
+            Shape::Rect { w: 3, h: 4 },
+        ],
+        limits: vec![("cpu".to_string(), 4), ("mem".to_string(), 1 << 40)]
+            .into_iter()
+            .collect(),
+        timeout: Duration::from_millis(90_500),
+    }
+}
+
+#[test]
+fn serialize() {
+    assert_eq!(
+        to_string(&config()).unwrap(),
+        r#"name "app"
+port 8080
+ratio 0.5
+tags "a" "b"
+matrix {
+    1 0
+    0 1
+}
+servers {
+    host "a.example.org"
+    port 1
+}
+servers {
+    host "b.example.org"
+    port 2
+}
+shapes "Point"
+shapes:Circle 1.5
+shapes:Line 1 2
+shapes:Rect {
+    w 3
+    h 4
+}
+limits {
+    cpu 4
+    mem 1099511627776L
+}
+timeout 00:01:30.500
+"#
+    );
+}
+
+#[test]
+fn round_trip() {
+    let text = to_string(&config()).unwrap();
+    assert_eq!(from_str::<Config>(&text).unwrap(), config());
+}
+
+#[test]
+fn attributes() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Root {
+        server: Vec<Server>,
+    }
+
+    let root: Root =
+        from_str("server host=\"a\" port=1\nserver host=\"b\" port=2").unwrap();
+    assert_eq!(
+        root.server,
+        vec![
+            Server {
+                host: "a".to_string(),
+                port: 1,
+            },
+            Server {
+                host: "b".to_string(),
+                port: 2,
+            },
+        ]
+    );
+}
+
+#[test]
+fn native_values() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Root {
+        date: Date,
+        time: DateTime,
+        naive: NaiveDateTime,
+        data: Vec<u8>,
+        big: i128,
+        text: String,
+        letter: char,
+    }
+
+    let text = "date 2019/01/02\ntime 2019/01/02 03:04:05-UTC\n\
+                naive 2019/01/02 03:04:05.500\ndata [AQID]\n\
+                big 170141183460469231731687303715884105727BD\n\
+                text \"2019-01-02T03:04:05.500+01:00\"\nletter '\\''\n";
+    let root: Root = from_str(text).unwrap();
+    assert_eq!(root.date, Date::from_ymd_opt(2019, 1, 2).unwrap());
+    assert_eq!(root.time.to_rfc3339(), "2019-01-02T03:04:05+00:00");
+    assert_eq!(format!("{:?}", root.naive), "2019-01-02T03:04:05.500");
+    assert_eq!(root.data, vec![1, 2, 3]);
+    assert_eq!(root.big, i128::MAX);
+    assert_eq!(root.letter, '\'');
+    // Strings which look like datetimes are written as such, and read back
+    // as the same strings.
+    assert_eq!(
+        to_string(&root).unwrap(),
+        text.replace(
+            "\"2019-01-02T03:04:05.500+01:00\"",
+            "2019/01/02 03:04:05.500-GMT+01:00"
+        )
+    );
+    assert_eq!(from_str::<Root>(&to_string(&root).unwrap()).unwrap(), root);
+}
+
+#[test]
+fn errors() {
+    assert!(from_str::<Server>("host \"a\"\nport \"b\"").is_err());
+    assert!(from_str::<Server>("host \"a\"\nhost \"b\"\nport 1").is_err());
+    assert!(from_str::<Server>("host").is_err());
+    assert!(to_string(&5).is_err());
+
+    #[derive(Serialize)]
+    struct Matrix {
+        rows: Vec<Vec<i32>>,
+    }
+    let matrix = Matrix {
+        rows: vec![vec![1], vec![]],
+    };
+    assert!(matches!(to_string(&matrix), Err(SerError::Unsupported(_))));
+
+    // Look-alikes of `std::time::Duration` must hold a valid duration.
+    #[derive(Serialize)]
+    #[serde(rename = "Duration")]
+    struct Span {
+        secs: i64,
+        nanos: u32,
+    }
+    #[derive(Serialize)]
+    struct Timeout {
+        timeout: Span,
+    }
+    let timeout = Timeout {
+        timeout: Span {
+            secs: 0,
+            nanos: 1_000_000_000,
+        },
+    };
+    assert!(matches!(to_string(&timeout), Err(SerError::Unsupported(_))));
+    // Negative durations are valid, but not as `std::time::Duration`s.
+    let timeout = Timeout {
+        timeout: Span { secs: -1, nanos: 0 },
+    };
+    assert_eq!(to_string(&timeout).unwrap(), "timeout -00:00:01\n");
+    #[derive(Debug, Deserialize)]
+    struct Wait {
+        #[allow(dead_code)]
+        timeout: Duration,
+    }
+    assert!(from_str::<Wait>("timeout -00:00:01").is_err());
+    assert!(from_str::<Wait>("timeout 00:00:01").is_ok());
+}
+
+#[test]
+fn model_round_trip() {
+    let root = parse_text(
+        "a \"text\" [AQID] 2019/01/02 2019/01/02 03:04:05.678-GMT+01:00 \
+         2019/01/02 03:04:05 -00:00:01.5 1d:02:03:04 12 3.5 on null \
+         key=\"value\" {\n    ns:b 1\n}",
+    )
+    .unwrap();
+    let json = serde_json::to_string(&root).unwrap();
+    assert_eq!(serde_json::from_str::<Tag>(&json).unwrap(), root);
+}
+
+#[test]
+fn model_tagged_values() {
+    let values = vec![
+        Value::String("2019-01-02".to_string()),
+        Value::Date(Date::from_ymd_opt(2019, 1, 2).unwrap()),
+        Value::Base64(vec![1, 2]),
+        Value::Duration(crate::Duration::seconds(1)),
+        Value::Null,
+    ];
+    assert_eq!(
+        serde_json::to_string(&values).unwrap(),
+        concat!(
+            r#"[{"String":"2019-01-02"},{"Date":"2019-01-02"},"#,
+            r#"{"Base64":[1,2]},{"Duration":{"secs":1,"nanos":0}},"Null"]"#
+        )
+    );
+}
@@ -0,0 +1,62 @@
+use super::gen;
+use super::utils::tree_config;
+use crate::{parse_text_with_spans, Attribute, Span, Tag, TagSpans, Value};
+
+use proptest::prelude::*;
+
+/// Checks that each span covers the text of the item it belongs to.
+fn check(text: &str, tag: &Tag, spans: &TagSpans) {
+    let slice = |span: &Span| {
+        let before = &text[..span.start.offset];
+        assert_eq!(span.start.line, before.matches('\n').count() + 1);
+        assert_eq!(
+            span.start.col,
+            before.rsplit('\n').next().unwrap().chars().count() + 1
+        );
+        &text[span.range()]
+    };
+
+    assert_eq!(
+        tag.namespace.as_deref(),
+        spans.namespace.as_ref().map(slice)
+    );
+    if let Some(name) = &spans.name {
+        assert_eq!(slice(name), tag.name);
+    }
+    assert_eq!(tag.values.len(), spans.values.len());
+    tag.values
+        .iter()
+        .zip(&spans.values)
+        .for_each(|(val, span)| {
+            assert_eq!(&slice(span).parse::<Value>().unwrap(), val);
+        });
+    assert_eq!(tag.attrs.len(), spans.attrs.len());
+    tag.attrs
+        .iter()
+        .zip(&spans.attrs)
+        .for_each(|(attr, spans)| {
+            assert_eq!(&slice(&spans.span).parse::<Attribute>().unwrap(), attr);
+            assert_eq!(slice(&spans.name), attr.name);
+            assert_eq!(
+                &slice(&spans.value).parse::<Value>().unwrap(),
+                &attr.value
+            );
+        });
+    assert_eq!(tag.tags.len(), spans.tags.len());
+    tag.tags
+        .iter()
+        .zip(&spans.tags)
+        .for_each(|(tag, spans)| check(text, tag, spans));
+}
+
+proptest! {
+    #![proptest_config(tree_config())]
+
+    #[test]
+    fn tagtree(test in gen::tagtree()) {
+        let (root, spans) = parse_text_with_spans(&test.text).unwrap();
+        assert_eq!(root.tags, test.result);
+        assert_eq!(spans.span.range(), 0..test.text.len());
+        check(&test.text, &root, &spans);
+    }
+}
@@ -0,0 +1,204 @@
+use super::gen;
+use super::utils::tree_config;
+use crate::stream::Events;
+use crate::{parse_events, parse_text, Error, Event, Result, Tag, Value};
+
+use proptest::prelude::*;
+
+use std::io;
+
+/// Builds the tags described by the events of the given text.
+fn build(text: &str) -> Result<Vec<Tag>> {
+    build_chunked(text, 8192)
+}
+
+/// Builds the tags described by the events of the given text, read at most
+/// `chunk` bytes at a time.
+fn build_chunked(text: &str, chunk: usize) -> Result<Vec<Tag>> {
+    let mut stack = vec![Tag::new(String::new())];
+    for event in Events::with_chunk(text.as_bytes(), chunk) {
+        match event? {
+            Event::StartTag { namespace, name } => {
+                let mut tag = Tag::new(name);
+                tag.namespace = namespace;
+                stack.push(tag);
+            }
+            Event::Value(value) => stack.last_mut().unwrap().values.push(value),
+            Event::Attribute(attr) => {
+                stack.last_mut().unwrap().attrs.push(attr)
+            }
+            Event::EndTag => {
+                let tag = stack.pop().unwrap();
+                stack.last_mut().unwrap().tags.push(tag);
+            }
+        }
+    }
+    assert_eq!(stack.len(), 1);
+    Ok(stack.pop().unwrap().tags)
+}
+
+/// Returns the line and column at which the error starts.
+fn position(err: &Error) -> (usize, usize) {
+    let pos = err.span().unwrap().start;
+    (pos.line, pos.col)
+}
+
+#[test]
+fn events() {
+    let text = concat!(
+        "ns:a 1 x=true {\n",
+        "    \"multi\n",
+        "line\" [aGVs\n",
+        "bG8=] /* a\n",
+        "comment */ \\\n",
+        "    2; b {}\n",
+        "}\n",
+    );
+    let events = parse_events(text.as_bytes())
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    let start = |namespace: Option<&str>, name: &str| Event::StartTag {
+        namespace: namespace.map(|ns| ns.to_string()),
+        name: name.to_string(),
+    };
+    assert_eq!(
+        events,
+        vec![
+            start(Some("ns"), "a"),
+            Event::Value(Value::from(1)),
+            Event::Attribute(("x".to_string(), true.into()).into()),
+            start(None, ""),
+            Event::Value("multi\nline".into()),
+            Event::Value(b"hello".to_vec().into()),
+            Event::Value(Value::from(2)),
+            Event::EndTag,
+            start(None, "b"),
+            Event::EndTag,
+            Event::EndTag,
+        ]
+    );
+    assert_eq!(build(text).unwrap(), parse_text(text).unwrap().tags);
+}
+
+#[test]
+fn errors() {
+    let err = build("a 1\nb {\n    c 2019/02/30\n}").unwrap_err();
+    match err {
+        Error::InvalidDate { .. } => assert_eq!(position(&err), (3, 7)),
+        err => panic!("unexpected error: {:?}", err),
+    }
+    let err = build("a {\n    b\n").unwrap_err();
+    assert_eq!(position(&err), (3, 1));
+    assert_eq!(err.message(), "Expected `}`");
+    let err = build("a {} b").unwrap_err();
+    assert_eq!(position(&err), (1, 6));
+    assert!(build("\"open\n\n").is_err());
+
+    // Parsing stops after the first error.
+    let mut events = parse_events("a @\nb".as_bytes());
+    assert!(events.next().unwrap().is_err());
+    assert!(events.next().is_none());
+}
+
+#[test]
+fn io() {
+    struct Failing;
+
+    impl io::Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("broken pipe"))
+        }
+    }
+
+    let mut events = parse_events(Failing);
+    match events.next() {
+        Some(Err(Error::Io(err))) => assert_eq!(err.to_string(), "broken pipe"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn chunks() {
+    let text = concat!(
+        "ns:a-- 1 -- comment\n",
+        "\"é\" x=[aGVs bG8=] // é\n",
+        "/* a\n*/ b; c\n",
+        "d ';' '}' '\\u007b' '\\n'\n",
+    );
+    for chunk in 1..8 {
+        assert_eq!(build_chunked(text, chunk).unwrap(), build(text).unwrap());
+    }
+    let err = build_chunked("a \"é\" @", 1).unwrap_err();
+    assert_eq!(position(&err), (1, 7));
+    match build_chunked("a \"\u{e9}", 1) {
+        Err(Error::Syntax { .. }) => {}
+        res => panic!("unexpected result: {:?}", res),
+    }
+    match Events::with_chunk(&b"a \"\xe9\""[..], 1).next() {
+        Some(Err(Error::Io(err))) => {
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData)
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+/// A single line which never ends is parsed as it is read, and errors on it
+/// are located by column.
+#[test]
+fn long_line() {
+    struct Endless(usize);
+
+    impl io::Read for Endless {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            for byte in buf.iter_mut() {
+                *byte = b"a 1;"[self.0 % 4];
+                self.0 += 1;
+            }
+            Ok(buf.len())
+        }
+    }
+
+    let events = parse_events(Endless(0)).take(30_000);
+    assert_eq!(events.filter(|event| event.is_ok()).count(), 30_000);
+
+    // A backslash before a multibyte character does not read to the end.
+    let text = io::Read::chain("a \\é\n".as_bytes(), Endless(0));
+    assert!(parse_events(text).next().unwrap().is_err());
+
+    let text = "a 1;".repeat(10_000) + " @";
+    let err = build(&text).unwrap_err();
+    assert_eq!(position(&err), (1, 40_002));
+}
+
+proptest! {
+    #[test]
+    fn never_panics(text in "[a-z0-9 \"{};\\[\\]=:/\\-#*\n\t`\\\\.]{0,64}") {
+        match parse_text(&text) {
+            Ok(tag) => assert_eq!(build(&text).unwrap(), tag.tags),
+            Err(_) => assert!(build(&text).is_err()),
+        }
+    }
+
+    #[test]
+    fn chunked(
+        text in "[a-z0-9 \"{};\\[\\]=:/\\-#*\n\t`\\\\.]{0,64}",
+        chunk in 1usize..8,
+    ) {
+        match build(&text) {
+            Ok(tags) => assert_eq!(build_chunked(&text, chunk).unwrap(), tags),
+            Err(err) => {
+                let chunked = build_chunked(&text, chunk).unwrap_err();
+                assert_eq!(chunked.span(), err.span());
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(tree_config())]
+
+    #[test]
+    fn tagtree(test in gen::tagtree()) {
+        assert_eq!(build(&test.text).unwrap(), test.result);
+    }
+}
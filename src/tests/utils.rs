@@ -9,7 +9,36 @@ use crate::{
     grammar::{ParseTree, Rule},
 };
 
+use proptest::prelude::ProptestConfig;
+
+use std::env;
 use std::fmt::Debug;
+use std::process::Command;
+
+/// The configuration of tests generating whole tag trees, which are large, so
+/// generating them is slow.
+pub fn tree_config() -> ProptestConfig {
+    ProptestConfig::with_cases(32)
+}
+
+/// Whether the current test runs in the given timezone (through the `TZ`
+/// environment variable).
+///
+/// Otherwise, the test with the given path is run again in that timezone, and
+/// must succeed.
+pub fn in_zone(test: &str, zone: &str) -> bool {
+    if env::var("TZ").as_deref() == Ok(zone) {
+        return true;
+    }
+    let out = Command::new(env::current_exe().unwrap())
+        .args(["--exact", test])
+        .env("TZ", zone)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success(), "{}", stdout);
+    false
+}
 
 /// The test type.
 ///
@@ -0,0 +1,222 @@
+use super::utils::in_zone;
+use crate::zone::{Zone, ABBREVIATIONS};
+use crate::{emit_text, parse_text, parse_text_with, Error, Tag, Timezone};
+use crate::{parse_events_with, parse_text_borrowed_with};
+use crate::{parse_text_recovering_with, parse_text_with_spans_with};
+use crate::{DefaultZone, Document, Event, ParseOptions, Value};
+
+use chrono::{FixedOffset, TimeZone};
+
+/// Parses the text, returning the values of its first tag.
+fn values(text: &str) -> Vec<Value> {
+    parse_text(text).unwrap().tags.remove(0).values
+}
+
+/// Creates a datetime value at the given offset, in hours.
+fn at(hours: f32, zone: Timezone, text: &str) -> Value {
+    let offset = FixedOffset::east_opt((hours * 3600.0) as i32).unwrap();
+    let naive =
+        chrono::NaiveDateTime::parse_from_str(text, "%Y/%m/%d %H:%M").unwrap();
+    Value::DateTime(offset.from_local_datetime(&naive).unwrap(), zone)
+}
+
+#[test]
+fn abbreviations() {
+    assert!(ABBREVIATIONS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert!(Zone::find("JST").is_some());
+    assert!(Zone::find("jst").is_none());
+}
+
+#[test]
+fn offsets() {
+    let text = "a 2019/01/02 03:04:00-UTC 2019/01/02 03:04:00-GMT \
+                2019/01/02 03:04:00-GMT-08 2019/01/02 03:04:00-GMT+05:30 \
+                2019/01/02 03:04:00-JST";
+    assert_eq!(
+        values(text),
+        vec![
+            at(0.0, Timezone::Utc, "2019/01/02 03:04"),
+            at(0.0, Timezone::Gmt, "2019/01/02 03:04"),
+            at(-8.0, Timezone::Gmt, "2019/01/02 03:04"),
+            at(5.5, Timezone::Gmt, "2019/01/02 03:04"),
+            at(9.0, Timezone::Named("JST".into()), "2019/01/02 03:04"),
+        ]
+    );
+}
+
+#[test]
+fn unknown() {
+    let text = "a 2019/01/02 03:04:05-Nowhere/Special 1";
+    match parse_text(text).unwrap_err() {
+        Error::UnknownTimezone { span } => {
+            assert_eq!(&text[span.range()], "Nowhere/Special")
+        }
+        err => panic!("unexpected error: {:?}", err),
+    }
+    // Zones which are not offsets are read as names.
+    assert!(matches!(
+        parse_text("a 2019/01/02 03:04:05-GMT+5"),
+        Err(Error::UnknownTimezone { .. })
+    ));
+}
+
+#[cfg(not(feature = "tz"))]
+#[test]
+fn iana_needs_feature() {
+    assert!(matches!(
+        parse_text("a 2019/01/02 03:04:05-America/Los_Angeles"),
+        Err(Error::UnknownTimezone { .. })
+    ));
+}
+
+#[cfg(feature = "tz")]
+#[test]
+fn iana() {
+    let zone = || Timezone::Named("America/Los_Angeles".into());
+    let text = "a 2019/01/02 03:04:00-America/Los_Angeles \
+                2019/07/02 03:04:00-America/Los_Angeles";
+    assert_eq!(
+        values(text),
+        vec![
+            at(-8.0, zone(), "2019/01/02 03:04"),
+            at(-7.0, zone(), "2019/07/02 03:04"),
+        ]
+    );
+
+    // Times skipped by the start of daylight saving time do not exist.
+    let text = "a 2019/03/10 02:30:00-America/Los_Angeles";
+    let err = parse_text(text).unwrap_err();
+    match err {
+        Error::NonexistentLocalTime { span } => {
+            assert_eq!(span.range(), 2..text.len())
+        }
+        ref err => panic!("unexpected error: {:?}", err),
+    }
+    let help = err.diagnostic(text).help().unwrap();
+    assert!(help.contains("daylight saving time"));
+    // Times repeated by the end of daylight saving time are ambiguous.
+    assert!(matches!(
+        parse_text("a 2019/11/03 01:30:00-America/Los_Angeles"),
+        Err(Error::AmbiguousLocalTime { .. })
+    ));
+}
+
+#[test]
+fn default_zone() {
+    let text = "a 2019/01/02 03:04:00 2019/01/02 03:04:00-UTC\n";
+    let parse = |zone| {
+        let options = ParseOptions::new().default_zone(zone);
+        parse_text_with(text, &options).unwrap()
+    };
+    let root = parse(DefaultZone::Utc);
+    assert_eq!(
+        root.tags[0].values,
+        vec![
+            at(0.0, Timezone::Local, "2019/01/02 03:04"),
+            at(0.0, Timezone::Utc, "2019/01/02 03:04"),
+        ]
+    );
+    let options = ParseOptions::new().default_zone(DefaultZone::Utc);
+    let (recovered, errors) = parse_text_recovering_with(text, &options);
+    assert_eq!((recovered, errors.len()), (root.clone(), 0));
+    let mut events = parse_events_with(text.as_bytes(), &options);
+    assert_eq!(
+        events.nth(1).unwrap().unwrap(),
+        Event::Value(root.tags[0].values[0].clone())
+    );
+    let borrowed = parse_text_borrowed_with(text, &options).unwrap();
+    assert_eq!(borrowed.into_owned(), root);
+    let (spanned, _) = parse_text_with_spans_with(text, &options).unwrap();
+    assert_eq!(spanned, root);
+    let doc = Document::parse_with(text, &options).unwrap();
+    assert_eq!(doc.to_tag_with(&options).unwrap(), root);
+    // The datetime is still written back without a timezone.
+    assert_eq!(emit_text(&root).unwrap(), text);
+
+    let offset = FixedOffset::west_opt(5 * 3600).unwrap();
+    assert_eq!(
+        parse(DefaultZone::Fixed(offset)).tags[0].values[0],
+        at(-5.0, Timezone::Local, "2019/01/02 03:04")
+    );
+}
+
+#[test]
+fn naive() {
+    let text = "a 2019/01/02 03:04:05 2019/01/02 03:04:05.678-UTC\n";
+    let naive = chrono::NaiveDate::from_ymd_opt(2019, 1, 2)
+        .unwrap()
+        .and_hms_opt(3, 4, 5)
+        .unwrap();
+    let root = parse_text(text).unwrap();
+    assert_eq!(root.tags[0].values[0], Value::NaiveDateTime(naive));
+    assert!(matches!(
+        root.tags[0].values[1],
+        Value::DateTime(_, Timezone::Utc)
+    ));
+    assert_eq!(root.tags[0].values[0].to_string(), "2019-01-02 03:04:05");
+    // No offset is invented when writing it back.
+    assert_eq!(emit_text(&root).unwrap(), text);
+
+    // Naive datetimes are never skipped over or repeated.
+    assert!(parse_text("a 2019/03/10 02:30:00").is_ok());
+    let explicit = ParseOptions::new().default_zone(DefaultZone::Naive);
+    assert_eq!(ParseOptions::default(), explicit);
+}
+
+#[test]
+fn local() {
+    if !in_zone("tests::zone::local", "America/Los_Angeles") {
+        return;
+    }
+    let options = ParseOptions::new().default_zone(DefaultZone::Local);
+    let parse = |text| parse_text_with(text, &options);
+    assert_eq!(
+        parse("a 2019/01/02 03:04:00").unwrap().tags[0].values,
+        vec![at(-8.0, Timezone::Local, "2019/01/02 03:04")]
+    );
+    assert!(matches!(
+        parse("a 2019/03/10 02:30:00"),
+        Err(Error::NonexistentLocalTime { .. })
+    ));
+
+    // Times repeated by the end of daylight saving time are ambiguous.
+    let text = "a 2019/11/03 01:30:00";
+    let err = parse(text).unwrap_err();
+    match err {
+        Error::AmbiguousLocalTime { span } => {
+            assert_eq!(span.range(), 2..text.len())
+        }
+        ref err => panic!("unexpected error: {:?}", err),
+    }
+    let help = err.diagnostic(text).help().unwrap();
+    assert!(help.contains("DefaultZone"));
+
+    // Other default zones do not depend on the machine.
+    let options = ParseOptions::new().default_zone(DefaultZone::Utc);
+    assert_eq!(
+        parse_text_with(text, &options).unwrap().tags[0].values,
+        vec![at(0.0, Timezone::Local, "2019/11/03 01:30")]
+    );
+}
+
+#[test]
+fn emit() {
+    let text = "a 2019/01/02 03:04:05-UTC 2019/01/02 03:04:05-GMT \
+                2019/01/02 03:04:05-GMT-08:00 2019/01/02 03:04:05-PST \
+                2019/01/02 03:04:05\n";
+    assert_eq!(emit_text(&parse_text(text).unwrap()).unwrap(), text);
+
+    // Names must be known, and give the offset of the datetime.
+    let dtime = FixedOffset::east_opt(0)
+        .unwrap()
+        .timestamp_opt(0, 0)
+        .unwrap();
+    for name in &["1st", "Nowhere", "JST"] {
+        let mut tag = Tag::new("a".into());
+        tag.values
+            .push(Value::DateTime(dtime, Timezone::Named(name.to_string())));
+        let mut root = Tag::new(String::new());
+        root.tags.push(tag);
+        assert!(emit_text(&root).is_err());
+    }
+}
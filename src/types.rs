@@ -1,18 +1,127 @@
+#[cfg(feature = "bigdecimal")]
+use crate::decimal::BigDecimal;
 use crate::parse;
 use crate::{grammar, grammar::Rule};
-use crate::{Error, Result};
+use crate::{Error, ParseOptions, Result};
 
 use itertools::Itertools;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::convert::TryFrom;
 use std::fmt;
 use std::iter;
 use std::str::FromStr;
-pub use std::time::Duration;
 
 /// `chrono`'s timezone-aware date-time struct.
 pub type DateTime = chrono::DateTime<chrono::FixedOffset>;
 /// `chrono`'s timezone-naive date struct.
 pub type Date = chrono::NaiveDate;
+/// `chrono`'s timezone-naive date-time struct.
+pub type NaiveDateTime = chrono::NaiveDateTime;
+/// `chrono`'s signed duration struct.
+pub type Duration = chrono::Duration;
+
+/// The timezone a datetime is written in.
+///
+/// The offset of a datetime is always resolved when it is parsed; this keeps
+/// track of the zone it was given in, so that it is written back the same way.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Timezone {
+    /// No timezone was given, so the datetime is in the default zone of the
+    /// `ParseOptions` it was parsed with.
+    Local,
+    /// `UTC`.
+    Utc,
+    /// `GMT`, with the offset of the datetime (e.g `GMT+05:30`).
+    Gmt,
+    /// A named timezone, such as `JST` or `America/Los_Angeles`.
+    ///
+    /// Common abbreviations are always known; the names of the IANA timezone
+    /// database need the `tz` feature.
+    Named(String),
+}
+
+/// The kind of an integer, given by its suffix.
+///
+/// These are the integer types of other SDLang implementations; the kind is
+/// kept alongside the integer so that it is written back the same way.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NumberKind {
+    /// A 32-bit integer, written without a suffix.
+    Int,
+    /// A 64-bit integer, written with the `L` suffix.
+    Long,
+    /// A big decimal, written with the `BD` suffix (e.g `5BD`). Integers of
+    /// this kind are limited to 128 bits; with the `bigdecimal` feature,
+    /// larger ones are read as `Value::BigDecimal`.
+    BigDecimal,
+}
+
+impl NumberKind {
+    /// Returns the smallest kind which holds the given integer.
+    pub fn of(num: i128) -> Self {
+        if i32::try_from(num).is_ok() {
+            NumberKind::Int
+        } else if i64::try_from(num).is_ok() {
+            NumberKind::Long
+        } else {
+            NumberKind::BigDecimal
+        }
+    }
+
+    /// Checks whether an integer is in the range of this kind.
+    pub fn holds(self, num: i128) -> bool {
+        match self {
+            NumberKind::Int => i32::try_from(num).is_ok(),
+            NumberKind::Long => i64::try_from(num).is_ok(),
+            NumberKind::BigDecimal => true,
+        }
+    }
+
+    /// Returns the suffix of integers of this kind.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            NumberKind::Int => "",
+            NumberKind::Long => "L",
+            NumberKind::BigDecimal => "BD",
+        }
+    }
+}
+
+/// The kind of a decimal, given by its suffix.
+///
+/// Like `NumberKind`, it is kept alongside the decimal so that it is written
+/// back the same way.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DecimalKind {
+    /// A 32-bit float, written with the `f` suffix.
+    Float,
+    /// A 64-bit double, written without a suffix or with the `d` suffix.
+    Double,
+}
+
+impl DecimalKind {
+    /// Checks whether a decimal is exactly representable in this kind.
+    pub fn holds(self, dec: f64) -> bool {
+        match self {
+            DecimalKind::Float => f64::from(dec as f32) == dec,
+            DecimalKind::Double => true,
+        }
+    }
+
+    /// Returns the suffix of decimals of this kind.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            DecimalKind::Float => "f",
+            DecimalKind::Double => "",
+        }
+    }
+}
 
 /// The value type encasing all possible SDLang value types.
 ///
@@ -22,22 +131,45 @@ pub type Date = chrono::NaiveDate;
 ///
 /// It implements `FromStr` to allow direct parsing, as well as `From` for all
 /// its subtypes (except `Null`).
+///
+/// With the `serde` feature, it is (de)serialized as an externally tagged
+/// enum (e.g `{"Date": "2019-01-02"}` in JSON), so that every type of value is
+/// kept distinct.
 #[derive(PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Value {
     /// Text types. Both normal and raw strings come under this.
     String(String),
+    /// A single character, such as `'a'` or `'\n'`.
+    Char(char),
     /// Base64 binary data, in the form of a series of bytes.
     Base64(Vec<u8>),
     /// Date. Not timezone-aware.
     Date(Date),
-    /// Date and time, timezone-aware.
-    DateTime(DateTime),
-    /// Durations of time.
-    Duration(Duration),
-    /// Integers.
-    Number(i128),
-    /// Decimals (floating-point).
-    Decimal(f64),
+    /// Date and time, timezone-aware, with the timezone it was given in.
+    DateTime(DateTime, Timezone),
+    /// Date and time without a timezone, left for the user to interpret.
+    NaiveDateTime(NaiveDateTime),
+    /// Durations of time, which may be negative.
+    ///
+    /// With the `serde` feature, it is (de)serialized like
+    /// `std::time::Duration`, with seconds and nanoseconds which are both
+    /// negative for negative durations.
+    Duration(
+        #[cfg_attr(feature = "serde", serde(with = "duration_parts"))] Duration,
+    ),
+    /// Integers, with the kind they were given as.
+    Number(i128, NumberKind),
+    /// Decimals (floating-point), with the kind they were given as. Floats
+    /// are widened to `f64`.
+    Decimal(f64, DecimalKind),
+    /// Decimals with the `BD` suffix, to arbitrary precision: those with a
+    /// fraction, and integers which do not fit in 128 bits.
+    ///
+    /// Other integers are `Value::Number` of the `BigDecimal` kind, as they
+    /// are read back. `Value::from` converts them so.
+    #[cfg(feature = "bigdecimal")]
+    BigDecimal(BigDecimal),
     /// Boolean values.
     Boolean(bool),
     /// Null.
@@ -52,12 +184,22 @@ impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self {
             Value::String(text) => write!(f, "\"{}\"", text),
+            Value::Char(ch) => write!(f, "'{}'", ch),
             Value::Base64(data) => write!(f, "{:x?}", data),
             Value::Date(date) => write!(f, "{}", date),
-            Value::DateTime(dtime) => write!(f, "{}", dtime),
-            Value::Duration(dur) => write!(f, "{:#?}", dur),
-            Value::Number(num) => write!(f, "{}", num),
-            Value::Decimal(dec) => write!(f, "{}", dec),
+            Value::DateTime(dtime, Timezone::Named(name)) => {
+                write!(f, "{} {}", dtime, name)
+            }
+            Value::DateTime(dtime, _) => write!(f, "{}", dtime),
+            Value::NaiveDateTime(dtime) => write!(f, "{}", dtime),
+            Value::Duration(dur) => write!(f, "{}", dur),
+            Value::Number(num, kind) => write!(f, "{}{}", num, kind.suffix()),
+            Value::Decimal(dec, DecimalKind::Float) => {
+                write!(f, "{}f", *dec as f32)
+            }
+            Value::Decimal(dec, DecimalKind::Double) => write!(f, "{}", dec),
+            #[cfg(feature = "bigdecimal")]
+            Value::BigDecimal(dec) => write!(f, "{}BD", dec),
             Value::Boolean(val) => write!(f, "{}", val),
             Value::Null => write!(f, "null"),
         }
@@ -78,7 +220,8 @@ impl FromStr for Value {
 
     /// Parses the text for a `Value`, returning a parse error on failure.
     fn from_str(s: &str) -> Result<Self> {
-        grammar::parse(Rule::value, s).and_then(parse::value)
+        grammar::parse(Rule::value, s)
+            .and_then(|tree| parse::value(tree, &ParseOptions::default()))
     }
 }
 
@@ -96,6 +239,13 @@ impl From<&str> for Value {
     }
 }
 
+impl From<char> for Value {
+    /// Creates a `Value::Char` from the given character.
+    fn from(v: char) -> Self {
+        Value::Char(v)
+    }
+}
+
 impl From<Vec<u8>> for Value {
     /// Creates a `Value::Base64` from the given data.
     fn from(v: Vec<u8>) -> Self {
@@ -111,9 +261,22 @@ impl From<Date> for Value {
 }
 
 impl From<DateTime> for Value {
-    /// Creates a `Value::DateTime` from the given date and time.
+    /// Creates a `Value::DateTime` from the given date and time, in `UTC` if
+    /// its offset is zero and in `GMT` otherwise.
     fn from(v: DateTime) -> Self {
-        Value::DateTime(v)
+        let zone = if v.offset().local_minus_utc() == 0 {
+            Timezone::Utc
+        } else {
+            Timezone::Gmt
+        };
+        Value::DateTime(v, zone)
+    }
+}
+
+impl From<NaiveDateTime> for Value {
+    /// Creates a `Value::NaiveDateTime` from the given date and time.
+    fn from(v: NaiveDateTime) -> Self {
+        Value::NaiveDateTime(v)
     }
 }
 
@@ -124,17 +287,104 @@ impl From<Duration> for Value {
     }
 }
 
+impl TryFrom<std::time::Duration> for Value {
+    type Error = chrono::OutOfRangeError;
+
+    /// Creates a `Value::Duration` from the given unsigned duration, failing
+    /// if it is too long for a `chrono` duration.
+    ///
+    /// `Duration::to_std` converts the other way around.
+    fn try_from(
+        v: std::time::Duration,
+    ) -> std::result::Result<Self, Self::Error> {
+        Duration::from_std(v).map(Value::Duration)
+    }
+}
+
+/// (De)serializes durations as seconds and nanoseconds.
+#[cfg(feature = "serde")]
+mod duration_parts {
+    use super::Duration;
+
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// The fields of a serialized duration, as for `std::time::Duration`.
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename = "Duration")]
+    struct Parts {
+        secs: i64,
+        nanos: i32,
+    }
+
+    pub fn serialize<S: Serializer>(
+        dur: &Duration,
+        ser: S,
+    ) -> Result<S::Ok, S::Error> {
+        Parts {
+            secs: dur.num_seconds(),
+            nanos: dur.subsec_nanos(),
+        }
+        .serialize(ser)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        de: D,
+    ) -> Result<Duration, D::Error> {
+        let parts = Parts::deserialize(de)?;
+        Duration::try_seconds(parts.secs)
+            .and_then(|secs| {
+                secs.checked_add(&Duration::nanoseconds(parts.nanos.into()))
+            })
+            .ok_or_else(|| de::Error::custom("duration out of range"))
+    }
+}
+
+impl From<i32> for Value {
+    /// Creates a `Value::Number` of the `Int` kind from the given integer.
+    fn from(v: i32) -> Self {
+        Value::Number(v.into(), NumberKind::Int)
+    }
+}
+
+impl From<i64> for Value {
+    /// Creates a `Value::Number` of the `Long` kind from the given integer.
+    fn from(v: i64) -> Self {
+        Value::Number(v.into(), NumberKind::Long)
+    }
+}
+
 impl From<i128> for Value {
-    /// Creates a `Value::Number` from the given integer.
+    /// Creates a `Value::Number` from the given integer, of the smallest kind
+    /// which holds it.
     fn from(v: i128) -> Self {
-        Value::Number(v)
+        Value::Number(v, NumberKind::of(v))
+    }
+}
+
+impl From<f32> for Value {
+    /// Creates a `Value::Decimal` of the `Float` kind from the given decimal.
+    fn from(v: f32) -> Self {
+        Value::Decimal(v.into(), DecimalKind::Float)
     }
 }
 
 impl From<f64> for Value {
-    /// Creates a `Value::Decimal` from the given decimal.
+    /// Creates a `Value::Decimal` of the `Double` kind from the given decimal.
     fn from(v: f64) -> Self {
-        Value::Decimal(v)
+        Value::Decimal(v, DecimalKind::Double)
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl From<BigDecimal> for Value {
+    /// Creates a `Value::BigDecimal` from the given decimal, or a
+    /// `Value::Number` of the `BigDecimal` kind if it is an integer which fits
+    /// in 128 bits.
+    fn from(v: BigDecimal) -> Self {
+        match v.to_i128() {
+            Some(num) => Value::Number(num, NumberKind::BigDecimal),
+            None => Value::BigDecimal(v),
+        }
     }
 }
 
@@ -153,6 +403,7 @@ impl From<bool> for Value {
 /// from a `(String, Value)` tuple (useful for collecting a set of attributes
 /// into a hash map).
 #[derive(PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Attribute {
     /// The name of the attribute.
     pub name: String,
@@ -187,7 +438,8 @@ impl FromStr for Attribute {
 
     /// Parses the text for an `Attribute`, returning a parse error on failure.
     fn from_str(s: &str) -> Result<Self> {
-        grammar::parse(Rule::attribute, s).and_then(parse::attribute)
+        grammar::parse(Rule::attribute, s)
+            .and_then(|tree| parse::attribute(tree, &ParseOptions::default()))
     }
 }
 
@@ -216,6 +468,7 @@ impl From<Attribute> for (String, Value) {
 ///
 /// All data in SDLang is stored through these.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Tag {
     /// The namespace (if any) of the tag.
     pub namespace: Option<String>,
@@ -287,25 +540,25 @@ impl Tag {
     }
 
     /// Finds the given attribute by name.
-    pub fn attr<'a, 'b>(&'a self, name: &'b str) -> Option<&'a Attribute> {
+    pub fn attr<'a>(&'a self, name: &str) -> Option<&'a Attribute> {
         self.attrs.iter().find(|a| a.name == name)
     }
 
     /// Finds the given attribute by name, returning a mutable reference.
-    pub fn attr_mut<'a, 'b>(
+    pub fn attr_mut<'a>(
         &'a mut self,
-        name: &'b str,
+        name: &str,
     ) -> Option<&'a mut Attribute> {
         self.attrs.iter_mut().find(|a| a.name == name)
     }
 
     /// Finds the given subtag by name.
-    pub fn tag<'a, 'b>(&'a self, name: &'b str) -> Option<&'a Tag> {
+    pub fn tag<'a>(&'a self, name: &str) -> Option<&'a Tag> {
         self.tags.iter().find(|t| t.name == name)
     }
 
     /// Finds the given tag by name, returning a mutable reference.
-    pub fn tag_mut<'a, 'b>(&'a mut self, name: &'b str) -> Option<&'a mut Tag> {
+    pub fn tag_mut<'a>(&'a mut self, name: &str) -> Option<&'a mut Tag> {
         self.tags.iter_mut().find(|t| t.name == name)
     }
 }
@@ -347,6 +600,7 @@ impl FromStr for Tag {
 
     /// Parses the text for a `Tag`, returning a parse error on failure.
     fn from_str(s: &str) -> Result<Self> {
-        grammar::parse(Rule::tag, s).and_then(parse::tag)
+        grammar::parse(Rule::tag, s)
+            .and_then(|tree| parse::tag(tree, &ParseOptions::default()))
     }
 }
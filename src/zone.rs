@@ -0,0 +1,96 @@
+//! Named timezones, such as `JST` or `America/Los_Angeles`.
+//!
+//! Common abbreviations are always known, and stand for a fixed offset. With
+//! the `tz` feature, the names of the IANA timezone database are known too;
+//! their offset depends on the date, as they follow daylight saving time.
+
+#[cfg(feature = "tz")]
+use chrono::Offset;
+use chrono::{FixedOffset, LocalResult, NaiveDateTime, TimeZone};
+
+use crate::DateTime;
+
+/// Abbreviations, with their offset from UTC in minutes.
+///
+/// Abbreviations which are shared by several zones stand for the one most
+/// commonly meant (e.g `CST` for the central US rather than China). The table
+/// is sorted, for binary searching.
+pub const ABBREVIATIONS: &[(&str, i32)] = &[
+    ("AEDT", 11 * 60),
+    ("AEST", 10 * 60),
+    ("AKDT", -8 * 60),
+    ("AKST", -9 * 60),
+    ("BST", 60),
+    ("CDT", -5 * 60),
+    ("CEST", 2 * 60),
+    ("CET", 60),
+    ("CST", -6 * 60),
+    ("EDT", -4 * 60),
+    ("EEST", 3 * 60),
+    ("EET", 2 * 60),
+    ("EST", -5 * 60),
+    ("HST", -10 * 60),
+    ("IST", 5 * 60 + 30),
+    ("JST", 9 * 60),
+    ("KST", 9 * 60),
+    ("MDT", -6 * 60),
+    ("MSK", 3 * 60),
+    ("MST", -7 * 60),
+    ("NZDT", 13 * 60),
+    ("NZST", 12 * 60),
+    ("PDT", -7 * 60),
+    ("PST", -8 * 60),
+    ("WEST", 60),
+    ("WET", 0),
+];
+
+/// A known timezone.
+pub enum Zone {
+    /// An abbreviation, for a fixed offset.
+    Fixed(FixedOffset),
+    /// A zone of the IANA database.
+    #[cfg(feature = "tz")]
+    Tz(chrono_tz::Tz),
+}
+
+impl Zone {
+    /// Finds the timezone with the given name.
+    pub fn find(name: &str) -> Option<Zone> {
+        if let Ok(i) = ABBREVIATIONS.binary_search_by_key(&name, |&(n, _)| n) {
+            let offset = FixedOffset::east_opt(ABBREVIATIONS[i].1 * 60)?;
+            return Some(Zone::Fixed(offset));
+        }
+        #[cfg(feature = "tz")]
+        if let Ok(tz) = name.parse() {
+            return Some(Zone::Tz(tz));
+        }
+        None
+    }
+
+    /// Converts a local datetime in this timezone.
+    ///
+    /// The datetime may be skipped over or repeated by a change in the offset
+    /// (such as the start or end of daylight saving time).
+    pub fn localize(&self, naive: &NaiveDateTime) -> LocalResult<DateTime> {
+        match self {
+            Zone::Fixed(offset) => offset.from_local_datetime(naive),
+            #[cfg(feature = "tz")]
+            Zone::Tz(tz) => tz
+                .from_local_datetime(naive)
+                .map(|dtime| dtime.fixed_offset()),
+        }
+    }
+
+    /// Returns the offset of this timezone at the given instant.
+    pub fn offset_at(&self, dtime: &DateTime) -> FixedOffset {
+        match self {
+            Zone::Fixed(offset) => {
+                offset.offset_from_utc_datetime(&dtime.naive_utc())
+            }
+            #[cfg(feature = "tz")]
+            Zone::Tz(tz) => {
+                tz.offset_from_utc_datetime(&dtime.naive_utc()).fix()
+            }
+        }
+    }
+}